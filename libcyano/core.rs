@@ -6,7 +6,7 @@ pub enum Option<T> {
 #[macro_export]
 macro_rules! unreachable {
     () => {
-        js!("alert('Cyano error: A codepath marked unreachable was reached.')");
+        js!("__cyano_trap('A codepath marked unreachable was reached.')");
 
         loop {}
     };
@@ -26,9 +26,54 @@ pub extern fn rust_eh_unwind_resume() {}
 #[no_mangle]
 pub extern fn rust_begin_panic(_msg: core::fmt::Arguments, _file: &'static str, _line: u32) -> ! {
     // TODO: Give the message here.
-    js!("alert('Panic!')");
+    js!("__cyano_trap('Panic!')");
 
     loop {}
 }
 
 */
+
+impl<T> Option<T> {
+    /// Take the value out of the option, leaving `None` in its place.
+    ///
+    /// ```rust
+    /// let mut x = Option::Some(2);
+    ///
+    /// if let Option::Some(y) = x.take() {
+    ///     assert!(y == 2);
+    /// }
+    /// match x {
+    ///     Option::Some(_) => unreachable!(),
+    ///     Option::None => {}
+    /// }
+    /// ```
+    pub fn take(&mut self) -> Option<T> {
+        // Read the old value through the reference and write back `None` (discriminant 1).
+        js!("var o=a0.get();a0.set({d:1});return o");
+
+        unreachable!();
+    }
+
+    /// Put `new` into the option, returning the old value.
+    ///
+    /// ```rust
+    /// let mut x = Option::None;
+    ///
+    /// match x.replace(1) {
+    ///     Option::Some(_) => unreachable!(),
+    ///     Option::None => {}
+    /// }
+    /// if let Option::Some(y) = x.replace(2) {
+    ///     assert!(y == 1);
+    /// }
+    /// if let Option::Some(y) = x {
+    ///     assert!(y == 2);
+    /// }
+    /// ```
+    pub fn replace(&mut self, new: T) -> Option<T> {
+        // Read the old value through the reference and write back `Some(a1)` (discriminant 0).
+        js!("var o=a0.get();a0.set({d:0,f0:a1});return o");
+
+        unreachable!();
+    }
+}
@@ -0,0 +1,40 @@
+//! A minimal application loop.
+//!
+//! The state is owned by the loop, which updates and renders it once per animation frame. A
+//! counter, which counts the seconds and draws them as a growing bar:
+//!
+//! ```rust
+//! struct Counter {
+//!     count: u32,
+//!     elapsed: f64,
+//! }
+//!
+//! fn update(counter: &mut Counter, dt: f64) {
+//!     counter.elapsed += dt;
+//!     if counter.elapsed >= 1000.0 {
+//!         counter.elapsed -= 1000.0;
+//!         counter.count += 1;
+//!     }
+//! }
+//!
+//! fn render(counter: &Counter) {
+//!     let ctx = canvas::get_context(&canvas::get_canvas("screen"));
+//!
+//!     canvas::clear_rect(&ctx, 0.0, 0.0, 640.0, 20.0);
+//!     canvas::fill_rect(&ctx, 0.0, 0.0, counter.count as f64 * 10.0, 20.0);
+//! }
+//!
+//! app::run(Counter { count: 0, elapsed: 0.0 }, update, render);
+//! ```
+
+/// Run the application with the initial state `init`.
+///
+/// On every animation frame, `update` is called with the state and the milliseconds passed since
+/// the last frame, followed by `render`. The state lives in a cell held by the loop, which both
+/// functions borrow in turn, so they never overlap. This returns right away, and the loop runs
+/// for as long as the page is open.
+pub fn run<S>(init: S, update: fn(&mut S, f64), render: fn(&S)) {
+    js!("var s=a0,c={get:function(){return s},set:function(x){s=x}},l=performance.now();\
+         function f(t){a1(c,Math.max(t-l,0));l=t;a2(c);requestAnimationFrame(f)}\
+         requestAnimationFrame(f)");
+}
@@ -0,0 +1,56 @@
+//! Typed buffers.
+//!
+//! These are backed by JavaScript typed arrays, and dereference to regular slices, so indexing
+//! lowers to plain `base[idx]` reads and writes. Typed arrays coerce on store, which gives the
+//! same wrapping as Rust's `as` casts:
+//!
+//! ```rust
+//! let mut buf = U8Buf::new(1);
+//! // Casts are emitted as is, so the typed array does the truncation.
+//! buf[0] = 300u32 as u8;
+//! assert!(buf[0] == 44);
+//! ```
+
+use core::ops::{Deref, DerefMut};
+
+macro_rules! typed_buf {
+    ($name:ident, $elem:ty, $js:expr) => {
+        pub struct $name {
+            _incomplete: [$elem; 0],
+        }
+
+        impl $name {
+            /// Create a zeroed buffer of `len` elements.
+            pub fn new(len: usize) -> $name {
+                js!(concat!("return new ", $js, "(a0)"));
+
+                unreachable!();
+            }
+        }
+
+        impl Deref for $name {
+            type Target = [$elem];
+
+            fn deref(&self) -> &[$elem] {
+                // Slices are represented by the reference to their array, so the reference to
+                // the buffer can be reused as is.
+                js!("return a0");
+
+                unreachable!();
+            }
+        }
+
+        impl DerefMut for $name {
+            fn deref_mut(&mut self) -> &mut [$elem] {
+                js!("return a0");
+
+                unreachable!();
+            }
+        }
+    };
+}
+
+typed_buf!(U8Buf, u8, "Uint8Array");
+typed_buf!(I32Buf, i32, "Int32Array");
+typed_buf!(F32Buf, f32, "Float32Array");
+typed_buf!(F64Buf, f64, "Float64Array");
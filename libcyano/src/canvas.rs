@@ -0,0 +1,50 @@
+//! Bindings to the 2D context of `<canvas>` elements.
+//!
+//! Drawing a red rectangle:
+//!
+//! ```rust
+//! let canvas = canvas::get_canvas("screen");
+//! let ctx = canvas::get_context(&canvas);
+//!
+//! canvas::set_fill_style(&ctx, "red");
+//! canvas::fill_rect(&ctx, 10.0, 10.0, 100.0, 50.0);
+//! ```
+
+/// A `<canvas>` element.
+pub struct Canvas {
+    _incomplete: (),
+}
+
+/// The 2D rendering context of a canvas.
+pub struct Context2d {
+    _incomplete: (),
+}
+
+/// Get the canvas element with the given id.
+pub fn get_canvas(id: &str) -> Canvas {
+    js!("return document.getElementById(a0)");
+
+    unreachable!();
+}
+
+/// Get the 2D rendering context of `canvas`.
+pub fn get_context(canvas: &Canvas) -> Context2d {
+    js!("return a0.get().getContext(\"2d\")");
+
+    unreachable!();
+}
+
+/// Set the CSS color (e.g. `"#ff0000"`) used by subsequent fills.
+pub fn set_fill_style(ctx: &Context2d, color: &str) {
+    js!("a0.get().fillStyle=a1");
+}
+
+/// Fill a rectangle with its upper left corner at `(x, y)`.
+pub fn fill_rect(ctx: &Context2d, x: f64, y: f64, w: f64, h: f64) {
+    js!("a0.get().fillRect(a1,a2,a3,a4)");
+}
+
+/// Clear a rectangle with its upper left corner at `(x, y)` to transparent.
+pub fn clear_rect(ctx: &Context2d, x: f64, y: f64, w: f64, h: f64) {
+    js!("a0.get().clearRect(a1,a2,a3,a4)");
+}
@@ -1,3 +1,10 @@
+/// An arbitrary JavaScript array, e.g. as returned by an imported function.
+///
+/// Use `Vec::from_js_array` to access its elements.
+pub struct JsArray {
+    _incomplete: (),
+}
+
 pub fn undefined<T>() -> T {
     js!("return undefined");
 
@@ -24,20 +31,65 @@ macro_rules! js {
     };
 }
 
+/// Log a value through some method of `console`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __console {
+    ($method:expr, $msg:expr) => {{
+        fn log<T>(_msg: T) {
+            js!(concat!("console.", $method, "(a0)"));
+        }
+
+        log($msg)
+    }};
+}
+
+/// Log a value to the console.
+#[macro_export]
+macro_rules! console_log {
+    ($msg:expr) => {
+        __console!("log", $msg)
+    };
+}
+
+/// Log an informational value to the console.
+#[macro_export]
+macro_rules! console_info {
+    ($msg:expr) => {
+        __console!("info", $msg)
+    };
+}
+
+/// Log a warning to the console.
+#[macro_export]
+macro_rules! console_warn {
+    ($msg:expr) => {
+        __console!("warn", $msg)
+    };
+}
+
+/// Log an error to the console.
+#[macro_export]
+macro_rules! console_error {
+    ($msg:expr) => {
+        __console!("error", $msg)
+    };
+}
+
 #[macro_export]
-macros_rules! export {
+macro_rules! export {
     (fn $name:ident($( $arg:ty ),*) -> $ret:ty { $body:block }) => {
         fn $name($( $arg ),*) -> $ret {
-            // Export the function itself to the global namespace.
-            js!(concat!("window.", stringify!($name), "=arguments.callee;");
+            // Export the function itself to the namespace of the program (`window` by default).
+            js!(concat!("__cyano_ns.", stringify!($name), "=arguments.callee;"));
 
             $body
         }
     };
     (pub fn $name:ident($( $arg:ty ),*) -> $ret:ty { $body:block }) => {
         pub fn $name($( $arg ),*) -> $ret {
-            // Export the function itself to the global namespace.
-            js!(concat!("window.", stringify!($name), "=arguments.callee;");
+            // Export the function itself to the namespace of the program (`window` by default).
+            js!(concat!("__cyano_ns.", stringify!($name), "=arguments.callee;"));
 
             $body
         }
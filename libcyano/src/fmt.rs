@@ -0,0 +1,24 @@
+//! Formatting of primitives.
+
+/// Format a float the way Rust's `Display` does.
+///
+/// JavaScript's `String(x)` differs from Rust in a few places: it switches to exponent notation
+/// for large and small magnitudes (`1e+21`), and names the infinities `Infinity`. This function
+/// takes the shortest round-tripping digits from JavaScript and lays them out like Rust, so
+/// `1.0` gives `1`, `0.1` gives `0.1` and `1e21` gives `1000000000000000000000`.
+///
+/// Precision and width flags (e.g. `{:.2}`) are not supported.
+pub fn f64_to_string(x: f64) -> &'static str {
+    js!("if(a0!==a0)return \"NaN\";\
+         if(a0===1/0)return \"inf\";\
+         if(a0===-1/0)return \"-inf\";\
+         if(a0===0)return 1/a0<0?\"-0\":\"0\";\
+         var s=String(a0),e=s.indexOf(\"e\");\
+         if(e<0)return s;\
+         var n=s[0]===\"-\",m=s.slice(n?1:0,e),i=m.indexOf(\".\"),d=m.replace(\".\",\"\"),\
+             p=(i<0?m.length:i)+ +s.slice(e+1),\
+             z=function(k){return new Array(k+1).join(\"0\")};\
+         return(n?\"-\":\"\")+(p<=0?\"0.\"+z(-p)+d:p>=d.length?d+z(p-d.length):d.slice(0,p)+\".\"+d.slice(p))");
+
+    unreachable!();
+}
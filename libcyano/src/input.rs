@@ -0,0 +1,46 @@
+//! Keyboard input.
+//!
+//! Handlers are closures, which are called with the pressed key, and keep their state between
+//! calls:
+//!
+//! ```rust
+//! let mut presses = 0;
+//! input::on_key_down(move |key: input::KeyCode| {
+//!     presses += 1;
+//!     console_log!(key.key);
+//! });
+//! ```
+
+/// A key press.
+pub struct KeyCode {
+    /// The value of the key, taking the layout and modifiers into account (e.g. `"a"`, `"A"` or
+    /// `"Enter"`).
+    pub key: &'static str,
+    /// The physical key, regardless of the layout (e.g. `"KeyA"`).
+    pub code: &'static str,
+}
+
+/// Call `handler` whenever a key is pressed.
+///
+/// Holding the key down repeats the event.
+pub fn on_key_down<F: FnMut(KeyCode)>(handler: F) {
+    listen("keydown", handler, call::<F>);
+}
+
+/// Call `handler` whenever a key is released.
+pub fn on_key_up<F: FnMut(KeyCode)>(handler: F) {
+    listen("keyup", handler, call::<F>);
+}
+
+/// Call `handler` through `call` whenever the keyboard event `event` fires.
+///
+/// The closure can't be called from JavaScript directly, as it is merely its environment, so
+/// `call` calls it instead. The listener holds the closure, which outlives this function.
+fn listen<F>(event: &'static str, handler: F, call: fn(&mut F, KeyCode)) {
+    js!("var h=a1,c={get:function(){return h},set:function(x){h=x}};\
+         document.addEventListener(a0,function(e){a2(c,{d:0,f0:e.key,f1:e.code})})");
+}
+
+fn call<F: FnMut(KeyCode)>(handler: &mut F, key: KeyCode) {
+    handler(key)
+}
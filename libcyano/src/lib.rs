@@ -1,3 +1,14 @@
 #[macro_reexport]
 mod ffi;
 mod vec;
+pub mod app;
+pub mod buf;
+pub mod canvas;
+pub mod fmt;
+pub mod input;
+pub mod map;
+pub mod sched;
+pub mod storage;
+pub mod string;
+pub mod text;
+pub mod time;
@@ -0,0 +1,97 @@
+//! Maps iterating in insertion order.
+//!
+//! Rust's `HashMap` iterates in a randomized order, so ported code accidentally depending on the
+//! order breaks in surprising ways. `OrderedMap` is backed by a JavaScript `Map`, which iterates
+//! in the order the keys were first inserted, so the order is explicit and the same on every run:
+//!
+//! ```rust
+//! let mut map = OrderedMap::new();
+//! map.insert("b", 2);
+//! map.insert("a", 1);
+//! // Overwriting keeps the position of the key.
+//! map.insert("b", 3);
+//!
+//! for (key, value) in map.iter() {
+//!     // Logs "b", 3, then "a", 1.
+//!     console_log!(*key);
+//!     console_log!(*value);
+//! }
+//! ```
+//!
+//! Keys are compared like in a JavaScript `Map`, so they should be primitives (integers, floats,
+//! `bool`s, `char`s or `&str`s). Aggregates are compared by identity rather than by value.
+
+use core::Option;
+
+pub struct OrderedMap<K, V> {
+    _incomplete: [(K, V); 0],
+}
+
+impl<K, V> OrderedMap<K, V> {
+    pub fn new() -> OrderedMap<K, V> {
+        js!("return new Map()");
+
+        // Fake value for rustc.
+        OrderedMap {
+            _incomplete: [],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        js!("return a0.get().size");
+
+        unreachable!();
+    }
+
+    /// Insert `value` under `key`, returning the previous value, if any.
+    ///
+    /// A new key goes last in the iteration order, whereas an overwritten key keeps its place.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        js!("var m=a0.get(),o=m.has(a1)?{d:0,f0:m.get(a1)}:{d:1};m.set(a1,a2);return o");
+
+        unreachable!();
+    }
+
+    /// Get a reference to the value under `key`, or `None` if there is none.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        js!("var m=a0.get(),k=a1.get();return m.has(k)?{d:0,f0:{get:function(){return m.get(k)}}}:{d:1}");
+
+        unreachable!();
+    }
+
+    /// Remove the value under `key`, returning it, if any.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        js!("var m=a0.get(),k=a1.get();if(!m.has(k))return{d:1};var v=m.get(k);m.delete(k);return{d:0,f0:v}");
+
+        unreachable!();
+    }
+
+    /// Iterate over the keys and values, in the order the keys were first inserted.
+    pub fn iter(&self) -> Iter<K, V> {
+        js!("return a0.get().entries()");
+
+        unreachable!();
+    }
+}
+
+/// An iterator over the entries of an `OrderedMap`.
+///
+/// This wraps the iterator of the JavaScript `Map`, so inserting during iteration is visible to
+/// it, unlike in Rust, where the borrow forbids it anyway.
+pub struct Iter<'a, K: 'a, V: 'a> {
+    _incomplete: [(&'a K, &'a V); 0],
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> ::core::option::Option<(&'a K, &'a V)> {
+        // This is libcore's `Option`, as returned by `Iterator::next`, where `None` goes first.
+        js!("var e=a0.get().next();\
+             if(e.done)return{d:0};\
+             var k=e.value[0],v=e.value[1];\
+             return{d:1,f0:[{get:function(){return k}},{get:function(){return v}}]}");
+
+        unreachable!();
+    }
+}
@@ -0,0 +1,28 @@
+//! Yielding to the event loop.
+//!
+//! Callbacks are plain function pointers:
+//!
+//! ```rust
+//! fn later() {
+//!     console_log!("after the current task");
+//! }
+//!
+//! sched::queue_microtask(later);
+//! console_log!("current task");
+//! ```
+//!
+//! Microtasks run as soon as the current task finishes, before rendering and before any other
+//! task (such as a `setTimeout(f, 0)` callback, which only runs after the next turn of the event
+//! loop). Idle callbacks run when the browser has nothing else to do, making them suitable for
+//! low priority work.
+
+/// Run `f` once the current task finishes.
+pub fn queue_microtask(f: fn()) {
+    js!("queueMicrotask(function(){a0()})");
+}
+
+/// Run `f` when the browser is idle.
+pub fn request_idle_callback(f: fn()) {
+    // The deadline argument is dropped.
+    js!("requestIdleCallback(function(){a0()})");
+}
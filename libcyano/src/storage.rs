@@ -0,0 +1,33 @@
+//! Bindings to the browser's `localStorage`.
+//!
+//! Values persist across page loads, and are stored as strings:
+//!
+//! ```rust
+//! storage::set("greeting", "hello");
+//!
+//! if let Option::Some(greeting) = storage::get("greeting") {
+//!     console_log!(greeting.as_str());
+//! }
+//! ```
+
+use core::Option;
+use string::String;
+
+/// Store `value` under `key`, overwriting any previous value.
+pub fn set(key: &str, value: &str) {
+    js!("localStorage.setItem(a0,a1)");
+}
+
+/// Get the value stored under `key`.
+///
+/// `localStorage` returns `null` for missing keys, which is mapped to `None`.
+pub fn get(key: &str) -> Option<String> {
+    js!("var v=localStorage.getItem(a0);return v===null?{d:1}:{d:0,f0:v}");
+
+    unreachable!();
+}
+
+/// Remove the value stored under `key`, if any.
+pub fn remove(key: &str) {
+    js!("localStorage.removeItem(a0)");
+}
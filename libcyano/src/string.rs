@@ -0,0 +1,23 @@
+//! Owned strings.
+
+/// An owned string, e.g. read at runtime by a binding.
+///
+/// This is a JavaScript string, like a `&str` is, so it is borrowed as one by `as_str`:
+///
+/// ```rust
+/// if let Option::Some(greeting) = storage::get("greeting") {
+///     console_log!(greeting.as_str());
+/// }
+/// ```
+pub struct String {
+    _incomplete: (),
+}
+
+impl String {
+    /// Get the contents of the string.
+    pub fn as_str(&self) -> &str {
+        js!("return a0.get()");
+
+        unreachable!();
+    }
+}
@@ -0,0 +1,35 @@
+//! UTF-8 conversion of strings, for interop with byte oriented APIs.
+//!
+//! Strings are JavaScript strings (UTF-16), so the conversion goes through `TextEncoder` and
+//! `TextDecoder`:
+//!
+//! ```rust
+//! let bytes = text::string_to_bytes("abc");
+//! assert!(bytes.len() == 3);
+//! assert!(text::bytes_to_string(&bytes).as_str() == "abc");
+//!
+//! // Multibyte characters take up several bytes.
+//! let bytes = text::string_to_bytes("h☃");
+//! assert!(bytes.len() == 4);
+//! assert!(text::bytes_to_string(&bytes).as_str() == "h☃");
+//! ```
+
+use string::String;
+use vec::Vec;
+
+/// Encode `s` as UTF-8.
+pub fn string_to_bytes(s: &str) -> Vec<u8> {
+    // `encode` gives a typed array, which can't grow, so it is copied into a regular array.
+    js!("return Array.from(new TextEncoder().encode(a0))");
+
+    unreachable!();
+}
+
+/// Decode the UTF-8 bytes `bytes` into a string.
+///
+/// Invalid sequences are replaced by U+FFFD.
+pub fn bytes_to_string(bytes: &[u8]) -> String {
+    js!("return new TextDecoder().decode(new Uint8Array(a0.get()))");
+
+    unreachable!();
+}
@@ -0,0 +1,24 @@
+//! Timing.
+//!
+//! The clock is monotonic, so it is suitable for measuring elapsed time:
+//!
+//! ```rust
+//! let start = time::now();
+//!
+//! let mut sum = 0;
+//! for i in 0..1000000 {
+//!     sum += i;
+//! }
+//!
+//! // Log the elapsed milliseconds.
+//! console_log!(time::now() - start);
+//! ```
+
+/// Get the number of milliseconds since the page was loaded.
+///
+/// This is `performance.now()`, which has sub-millisecond precision (browsers may coarsen it).
+pub fn now() -> f64 {
+    js!("return performance.now()");
+
+    unreachable!();
+}
@@ -1,5 +1,6 @@
-use ffi;
+use ffi::{self, JsArray};
 use core::Option;
+use core::cmp::Ordering;
 
 pub struct Vec<T> {
     _incomplete: [T; 0],
@@ -15,12 +16,66 @@ impl Vec {
         }
     }
 
+    /// Wrap the JavaScript array `arr` as a vector, without copying it.
+    ///
+    /// The vector aliases the array, so mutations through either of them are visible through the
+    /// other.
+    pub fn from_js_array(arr: JsArray) -> Vec {
+        js!("return a0");
+
+        // Fake value for rustc.
+        Vec {
+            _incomplete: [],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        js!("return a0.get().length");
+
+        unreachable!();
+    }
+
+    /// Get a reference to the element at `idx`, or `None` if it is out of bounds.
+    pub fn get(&self, idx: usize) -> Option<&T> {
+        js!("var a=a0.get();return a1<a.length?{d:0,f0:{get:function(){return a[a1]}}}:{d:1}");
+
+        unreachable!();
+    }
+
+    /// Get a mutable reference to the element at `idx`, or `None` if it is out of bounds.
+    ///
+    /// ```rust
+    /// let mut vec = Vec::new();
+    /// vec.push(1);
+    /// vec.push(2);
+    ///
+    /// if let Option::Some(x) = vec.get_mut(1) {
+    ///     *x = 3;
+    /// }
+    /// if let Option::Some(x) = vec.pop() {
+    ///     assert!(x == 3);
+    /// }
+    /// ```
+    pub fn get_mut(&mut self, idx: usize) -> Option<&mut T> {
+        js!("var a=a0.get();\
+             return a1<a.length?{d:0,f0:{get:function(){return a[a1]},set:function(x){a[a1]=x}}}:{d:1}");
+
+        unreachable!();
+    }
+
     pub fn push(&mut self, elem: T) {
-        js!("a0.push(a1)")
+        js!("a0.get().push(a1)")
+    }
+
+    /// Append clones of the elements of `s`.
+    ///
+    /// Aggregates are deep copied, like `Clone` does, so the vector doesn't alias the slice.
+    pub fn extend_from_slice(&mut self, s: &[T]) {
+        js!("var v=a0.get();a1.get().forEach(function(x){v.push(__cyano_dup(x))})")
     }
 
     pub fn pop(&mut self) -> Option<T> {
-        let res = js!("a0.pop()");
+        let res = js!("a0.get().pop()");
 
         if res == ffi::undefined() {
             Option::None
@@ -28,4 +83,55 @@ impl Vec {
             Option::Some(res)
         }
     }
+
+    /// Sort the vector with the comparator `cmp`.
+    ///
+    /// This uses the JavaScript `sort`, which is stable in modern engines (as required since
+    /// ES2019), so equal elements keep their order, like in Rust. Sorting descending:
+    ///
+    /// ```rust
+    /// let mut vec = Vec::new();
+    /// vec.extend_from_slice(&[2, 3, 1]);
+    /// vec.sort_by(|a, b| b.cmp(a));
+    ///
+    /// while let Option::Some(x) = vec.pop() {
+    ///     // Logs 1, 2, then 3.
+    ///     console_log!(x);
+    /// }
+    /// ```
+    pub fn sort_by<F: FnMut(&T, &T) -> Ordering>(&mut self, mut cmp: F) {
+        sort(self, &mut cmp, compare::<T, F>);
+    }
+}
+
+/// Sort `vec` with the JavaScript `sort`, calling `cmp` through `compare`.
+///
+/// The closure can't be called from JavaScript directly, as it is merely its environment, so
+/// `compare` calls it instead. The discriminant of an `Ordering` is -1, 0 or 1, which is the
+/// result the JavaScript comparator is expected to return.
+fn sort<T, F>(vec: &mut Vec<T>, cmp: &mut F, compare: fn(&mut F, &T, &T) -> Ordering) {
+    js!("a0.get().sort(function(x,y){return a2(a1,{get:function(){return x}},{get:function(){return y}}).d})")
+}
+
+fn compare<T, F: FnMut(&T, &T) -> Ordering>(cmp: &mut F, x: &T, y: &T) -> Ordering {
+    cmp(x, y)
+}
+
+/// Concatenate clones of the vectors `vecs` into a new vector.
+///
+/// ```rust
+/// let mut a = Vec::new();
+/// a.extend_from_slice(&[1, 2]);
+/// let mut b = Vec::new();
+/// b.extend_from_slice(&[3]);
+///
+/// assert!(vec::concat(&[a, b]).len() == 3);
+/// ```
+pub fn concat(vecs: &[Vec<T>]) -> Vec<T> {
+    js!("var r=[];a0.get().forEach(function(v){v.forEach(function(x){r.push(__cyano_dup(x))})});return r");
+
+    // Fake value for rustc.
+    Vec {
+        _incomplete: [],
+    }
 }
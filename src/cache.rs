@@ -0,0 +1,173 @@
+//! Caching of compiled functions across compilations.
+//!
+//! Functions are keyed by the name of their instance, which is derived from the `DefId` and the
+//! hash of the type arguments. Every entry carries a hash of the inputs of the function (its MIR,
+//! the MIR of the callees it may inline, and the configuration), so entries of functions, which
+//! have changed since, are not reused.
+
+use std::collections::HashMap;
+
+/// A compiled function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Entry {
+    /// The hash of the inputs the function was compiled from.
+    hash: u64,
+    /// The JavaScript of the function, before its literals are hoisted.
+    js: String,
+    /// The names of the instances referred to by the function, which must be written as well.
+    deps: Vec<String>,
+}
+
+/// The cache of compiled functions.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FnCache {
+    fns: HashMap<String, Entry>,
+}
+
+impl FnCache {
+    /// Get the JavaScript and the dependencies of the function `name`, compiled from inputs of
+    /// hash `hash`.
+    ///
+    /// Returns `None` if there is no entry for `name`, or if its inputs have changed.
+    pub fn get(&self, name: &str, hash: u64) -> Option<(&str, &[String])> {
+        self.fns.get(name).and_then(|entry| if entry.hash == hash {
+            Some((&*entry.js, &*entry.deps))
+        } else {
+            None
+        })
+    }
+
+    /// Get the JavaScript of the function `name`, compiled from inputs of hash `hash`, along with
+    /// its dependencies, as resolved by `resolve`.
+    ///
+    /// Returns `None` if the function must be compiled anew, i.e. if `get` has no entry for it,
+    /// or if any of its dependencies can't be resolved (e.g. because the item is gone).
+    pub fn reuse<T, F>(&self, name: &str, hash: u64, resolve: F) -> Option<(String, Vec<T>)>
+        where F: FnMut(&String) -> Option<T> {
+        self.get(name, hash).and_then(|(js, deps)| {
+            deps.iter().map(resolve).collect::<Option<Vec<_>>>().map(|deps| (js.to_string(), deps))
+        })
+    }
+
+    /// Cache the JavaScript `js` of the function `name`, compiled from inputs of hash `hash`.
+    pub fn insert(&mut self, name: String, hash: u64, js: String, deps: Vec<String>) {
+        self.fns.insert(name, Entry {
+            hash: hash,
+            js: js,
+            deps: deps,
+        });
+    }
+
+    /// The number of cached functions.
+    pub fn len(&self) -> usize {
+        self.fns.len()
+    }
+
+    /// Serialize the cache, so it can be stored and loaded by `parse` in a later compilation.
+    ///
+    /// Every entry is a header line (the name, the hash, the length of the JavaScript, and the
+    /// dependencies, separated by spaces), followed by the JavaScript itself. The JavaScript may
+    /// contain newlines (e.g. in embedded JS), hence the explicit length.
+    pub fn serialize(&self) -> String {
+        let mut out = String::new();
+        for (name, entry) in &self.fns {
+            out.push_str(&format!("{} {:x} {}", name, entry.hash, entry.js.len()));
+            for dep in &entry.deps {
+                out.push(' ');
+                out.push_str(dep);
+            }
+            out.push('\n');
+            out.push_str(&entry.js);
+            out.push('\n');
+        }
+
+        out
+    }
+
+    /// Load a cache serialized by `serialize`.
+    ///
+    /// Returns `None` if `s` is malformed.
+    pub fn parse(mut s: &str) -> Option<FnCache> {
+        let mut cache = FnCache::default();
+        while !s.is_empty() {
+            let header_len = match s.find('\n') {
+                Some(len) => len,
+                None => return None,
+            };
+            let mut header = s[..header_len].split(' ');
+            let name = match header.next() {
+                Some(name) => name.to_string(),
+                None => return None,
+            };
+            let hash = match header.next().and_then(|x| u64::from_str_radix(x, 16).ok()) {
+                Some(hash) => hash,
+                None => return None,
+            };
+            let len = match header.next().and_then(|x| x.parse::<usize>().ok()) {
+                Some(len) => len,
+                None => return None,
+            };
+            let deps = header.map(|dep| dep.to_string()).collect();
+
+            s = &s[header_len + 1..];
+            // The JavaScript must be followed by the newline ending the entry.
+            if s.len() <= len || !s.is_char_boundary(len) || s.as_bytes()[len] != b'\n' {
+                return None;
+            }
+            cache.insert(name, hash, s[..len].to_string(), deps);
+            s = &s[len + 1..];
+        }
+
+        Some(cache)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_get() {
+        let mut cache = FnCache::default();
+        cache.insert("d1_0".to_string(), 42, "function d1_0(){}".to_string(), vec!["d2_0".to_string()]);
+
+        assert_eq!(cache.get("d1_0", 42), Some(("function d1_0(){}", &["d2_0".to_string()][..])));
+        // The function has changed since.
+        assert_eq!(cache.get("d1_0", 43), None);
+        assert_eq!(cache.get("d2_0", 42), None);
+    }
+
+    #[test]
+    fn test_reuse() {
+        let mut cache = FnCache::default();
+        cache.insert("d1_0".to_string(), 1, "function d1_0(){d2_0()}".to_string(), vec!["d2_0".to_string()]);
+        cache.insert("d3_0".to_string(), 3, "function d3_0(){d4_0()}".to_string(), vec!["d4_0".to_string()]);
+        let cache = FnCache::parse(&cache.serialize()).unwrap();
+
+        // The instances, which are still around.
+        let resolve = |name: &String| match &**name {
+            "d1_0" => Some(1),
+            "d2_0" => Some(2),
+            "d3_0" => Some(3),
+            _ => None,
+        };
+        assert_eq!(cache.reuse("d1_0", 1, resolve), Some(("function d1_0(){d2_0()}".to_string(), vec![2])));
+        assert_eq!(cache.reuse("d1_0", 2, resolve), None);
+        assert_eq!(cache.reuse("d2_0", 2, resolve), None);
+        // The dependency is gone.
+        assert_eq!(cache.reuse("d3_0", 3, resolve), None);
+    }
+
+    #[test]
+    fn test_serialize() {
+        let mut cache = FnCache::default();
+        cache.insert("d1_0".to_string(), 0xff, "function d1_0(){f(\n)}".to_string(),
+                     vec!["d2_0".to_string(), "d3_0_1a".to_string()]);
+        cache.insert("d2_0".to_string(), 7, String::new(), Vec::new());
+
+        assert_eq!(FnCache::parse(&cache.serialize()), Some(cache));
+        assert_eq!(FnCache::parse(""), Some(FnCache::default()));
+        assert_eq!(FnCache::parse("d1_0 ff 100\nfunction"), None);
+        assert_eq!(FnCache::parse("d1_0 zz 0\n\n"), None);
+    }
+}
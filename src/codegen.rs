@@ -1,8 +1,12 @@
 use rustc::hir::def_id::DefId;
 use rustc::middle::const_val::ConstVal;
 use rustc::mir::repr;
+use rustc::ty::subst::Substs;
+use rustc_const_math::{ConstFloat, ConstInt};
 use rustc_data_structures::indexed_vec::Idx;
+use std::collections::hash_map::DefaultHasher;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 
 pub struct Arg(pub repr::Arg);
 
@@ -36,14 +40,117 @@ impl fmt::Display for Field {
     }
 }
 
+pub struct Int(pub ConstInt);
+
+impl fmt::Display for Int {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_negative() {
+            // Reinterpret the bits, as the value is wrapped when converted.
+            write!(f, "{}", self.0.to_u64_unchecked() as i64)
+        } else {
+            write!(f, "{}", self.0.to_u64_unchecked())
+        }
+    }
+}
+
+pub struct Float(pub ConstFloat);
+
+impl fmt::Display for Float {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let x = match self.0 {
+            ConstFloat::F32(x) => x as f64,
+            ConstFloat::F64(x) | ConstFloat::FInfer { f64: x, .. } => x,
+        };
+
+        // Rust writes these as `NaN` and `inf`, which aren't the JavaScript names.
+        if x.is_nan() {
+            write!(f, "NaN")
+        } else if x.is_infinite() {
+            write!(f, "{}Infinity", if x < 0.0 { "-" } else { "" })
+        } else {
+            write!(f, "{:?}", x)
+        }
+    }
+}
+
 pub struct Item(pub DefId);
 
 impl fmt::Display for Item {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "d{:x}_{:x}(", self.0.index.as_u32(), self.0.krate)
+        write!(f, "d{:x}_{:x}", self.0.index.as_u32(), self.0.krate)
+    }
+}
+
+/// An instance of an item with some type arguments.
+///
+/// Items without type arguments are named like plain items, whereas the instances of generic
+/// items are distinguished by a hash of their type arguments.
+pub struct Instance<'a>(pub DefId, pub &'a Substs<'a>);
+
+impl<'a> fmt::Display for Instance<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let types: Vec<_> = self.1.types().map(|ty| ty.to_string()).collect();
+        write!(f, "{}{}", Item(self.0), TypeArgs(&types))
+    }
+}
+
+/// The suffix of the name of an instance with some type arguments, given as written in Rust.
+pub struct TypeArgs<'a>(pub &'a [String]);
+
+impl<'a> fmt::Display for TypeArgs<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_empty() {
+            return Ok(());
+        }
+
+        let mut hasher = DefaultHasher::new();
+        for ty in self.0 {
+            ty.hash(&mut hasher);
+        }
+        write!(f, "_{:x}", hasher.finish())
     }
 }
 
+/// JavaScript keywords and reserved words, which can't be used as identifiers.
+const RESERVED: &'static [&'static str] = &[
+    "arguments", "await", "break", "case", "catch", "class", "const", "continue", "debugger",
+    "default", "delete", "do", "else", "enum", "eval", "export", "extends", "false", "finally",
+    "for", "function", "if", "implements", "import", "in", "instanceof", "interface", "let", "new",
+    "null", "package", "private", "protected", "public", "return", "static", "super", "switch",
+    "this", "throw", "true", "try", "typeof", "var", "void", "while", "with", "yield",
+];
+
+/// Globals of JavaScript and its hosts, which the runtime, the generated code, or embedded JS may
+/// refer to, so items must not shadow them.
+const GLOBALS: &'static [&'static str] = &[
+    "Array", "ArrayBuffer", "DataView", "Date", "Error", "Infinity", "JSON", "Map", "Math", "NaN",
+    "Number", "Object", "Promise", "Proxy", "Reflect", "Set", "String", "Symbol", "TextDecoder",
+    "TextEncoder", "TypeError", "WeakMap", "alert", "console", "document", "globalThis", "isNaN",
+    "parseFloat", "parseInt", "performance", "process", "require", "requestAnimationFrame", "self",
+    "undefined", "window",
+];
+
+/// Check if `name` can be used as is as the JavaScript name of an unmangled item.
+///
+/// Besides being a valid identifier, the name must not shadow a global, nor collide with the
+/// names of the runtime helpers, or with the names the compiler gives items and locals (e.g. `add`
+/// is also the name of argument `0xdd`).
+pub fn is_exact_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    let first = match chars.next() {
+        Some(c) => c,
+        None => return false,
+    };
+    let rest = chars.as_str();
+
+    (first.is_alphabetic() || first == '_' || first == '$')
+        && rest.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+        && !RESERVED.contains(&name)
+        && !GLOBALS.contains(&name)
+        && !name.starts_with("__cyano_")
+        && !("avtdcgr".contains(first) && rest.chars().all(|c| c.is_digit(16) || c == '_'))
+}
+
 pub struct LvalueGet<'a>(pub &'a repr::Lvalue<'a>);
 
 impl<'a> fmt::Display for LvalueGet<'a> {
@@ -59,6 +166,10 @@ impl<'a> fmt::Display for LvalueGet<'a> {
                     &repr::ProjectionElem::Deref => write!(f, "{}.get()", LvalueGet(base)),
                     &repr::ProjectionElem::Field(field, _) => write!(f, "{}.{}", LvalueGet(base), Field(field)),
                     &repr::ProjectionElem::Index(ref idx) => write!(f, "{}[{}]", LvalueGet(base), Operand(idx)),
+                    // All variants are the same object, so a downcast is a no-op. In particular,
+                    // a downcast of a dereference (as in matching on `&Option<T>`) reads the
+                    // fields through the reference.
+                    &repr::ProjectionElem::Downcast(..) => write!(f, "{}", LvalueGet(base)),
                     _ => unimplemented!(),
                 }
         }
@@ -79,34 +190,46 @@ impl<'a> fmt::Display for LvalueSet<'a> {
                 &repr::ProjectionElem::Deref => write!(f, "{}.set({})", LvalueGet(base), self.1),
                 &repr::ProjectionElem::Field(field, _) => write!(f, "{}.{}={}", LvalueGet(base), Field(field), self.1),
                 &repr::ProjectionElem::Index(ref idx) => write!(f, "{}[{}]={}", LvalueGet(base), Operand(idx), self.1),
+                &repr::ProjectionElem::Downcast(..) => write!(f, "{}", LvalueSet(base, self.1)),
                 _ => unimplemented!(),
             },
         }
     }
 }
 
+#[derive(Clone, Copy)]
 pub enum Expr<'a> {
     Rvalue(&'a repr::Rvalue<'a>),
-    Call(&'a repr::Lvalue<'a>, &'a [repr::Operand<'a>]),
+    /// A call of some function value with some arguments.
+    Call(&'a repr::Operand<'a>, &'a [repr::Operand<'a>]),
+    /// Raw JavaScript.
+    Raw(&'a str),
 }
 
 impl<'a> fmt::Display for Expr<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             &Expr::Rvalue(rvalue) => write!(f, "{}", Rvalue(rvalue)),
-            &Expr::Call(lvalue, args) => {
-                // Asign the result to some lvalue.
-                write!(f, "{}(", LvalueGet(lvalue))?;
+            // The callee can be any function value, be it an item or a function pointer.
+            &Expr::Call(func, args) => write!(f, "{}({})", Operand(func), Args(args)),
+            &Expr::Raw(js) => write!(f, "{}", js),
+        }
+    }
+}
 
-                // List the argument.
-                for i in args {
-                    write!(f, "{},", Operand(i))?;
-                }
+/// A comma-separated list of arguments.
+pub struct Args<'a>(pub &'a [repr::Operand<'a>]);
 
-                // Close the argument list.
-                write!(f, ")")
-            },
+impl<'a> fmt::Display for Args<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (n, i) in self.0.iter().enumerate() {
+            if n != 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", Operand(i))?;
         }
+
+        Ok(())
     }
 }
 
@@ -115,9 +238,9 @@ pub struct Literal<'a>(pub &'a repr::Literal<'a>);
 impl<'a> fmt::Display for Literal<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.0 {
-            &repr::Literal::Item { def_id, .. } => write!(f, "{}", Item(def_id)),
+            &repr::Literal::Item { def_id, substs } => write!(f, "{}", Instance(def_id, substs)),
             &repr::Literal::Value { ref value } => match value {
-                &ConstVal::Integral(int) => write!(f, "{}", int.to_u64_unchecked()),
+                &ConstVal::Integral(int) => write!(f, "{}", Int(int)),
                 &ConstVal::Str(ref string) =>
                     if string.starts_with("[js?") && string.ends_with("?js]") {
                         // We output the JavaScript without quotes, meaning that we embeded raw JS.
@@ -127,6 +250,13 @@ impl<'a> fmt::Display for Literal<'a> {
                         write!(f, "\"{}\"", string.escape_default())
                     },
                 &ConstVal::Bool(b) => write!(f, "{}", b),
+                // Results of const-evaluated expressions (such as calls to `const fn`s) are
+                // literals as well.
+                &ConstVal::Float(x) => write!(f, "{}", Float(x)),
+                // Characters are represented by their code points.
+                &ConstVal::Char(c) => write!(f, "{}", c as u32),
+                // Function values are simply references to the function.
+                &ConstVal::Function(def_id) => write!(f, "{}", Item(def_id)),
                 _ => unimplemented!(),
             },
             _ => unimplemented!(),
@@ -145,13 +275,46 @@ impl<'a> fmt::Display for Operand<'a> {
     }
 }
 
+/// Evaluate the integer operation `binop` of the operands `x` and `y`, if both are constant.
+///
+/// Operations, which overflow or divide by zero, are left to be evaluated (and checked) at
+/// runtime.
+pub fn fold(binop: repr::BinOp, x: &repr::Operand, y: &repr::Operand) -> Option<ConstInt> {
+    match (x, y) {
+        (&repr::Operand::Constant(repr::Constant {
+            literal: repr::Literal::Value { value: ConstVal::Integral(x) }, ..
+        }), &repr::Operand::Constant(repr::Constant {
+            literal: repr::Literal::Value { value: ConstVal::Integral(y) }, ..
+        })) => fold_int(binop, x, y),
+        _ => None,
+    }
+}
+
+/// Evaluate the integer operation `binop` of `x` and `y`, unless it overflows.
+///
+/// Comparisons aren't folded, as they give bools rather than integers.
+pub fn fold_int(binop: repr::BinOp, x: ConstInt, y: ConstInt) -> Option<ConstInt> {
+    match binop {
+        repr::BinOp::Add => x + y,
+        repr::BinOp::Sub => x - y,
+        repr::BinOp::Mul => x * y,
+        repr::BinOp::Div => x / y,
+        repr::BinOp::Rem => x % y,
+        repr::BinOp::BitXor => x ^ y,
+        repr::BinOp::BitAnd => x & y,
+        repr::BinOp::BitOr => x | y,
+        repr::BinOp::Shl => x << y,
+        repr::BinOp::Shr => x >> y,
+        _ => return None,
+    }.ok()
+}
+
 fn binop_to_js(binop: repr::BinOp) -> &'static str {
     match binop {
         repr::BinOp::Add => "+",
         repr::BinOp::Sub => "-",
         repr::BinOp::Mul => "*",
-        // FIXME: Integer division doesn't not round down, but instead coerces to floats,
-        // giving results different from Rust's.
+        // Note that integer division is lowered separately, as it must round towards zero.
         repr::BinOp::Div => "/",
         repr::BinOp::Rem => "%",
         // FIXME: In JavaScript, using these operations on boolean values will convert them
@@ -177,40 +340,85 @@ fn unop_to_js(unop: repr::UnOp) -> char {
     }
 }
 
+/// A reference to some lvalue, borrowed in some way.
+///
+/// JavaScript doesn't have first class pointers, however it is possible to emulate them through
+/// closures. The basic idea is to let a setter and getter closure capture the lvalue, and then
+/// access it as an alias through these methods. It's pretty hacky, but it works.
+pub struct Ref<'a>(pub repr::BorrowKind, pub &'a repr::Lvalue<'a>);
+
+impl<'a> fmt::Display for Ref<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.0, self.1) {
+            // References into array elements. The array and the index are captured when the
+            // reference is taken, as the index operand may be reassigned while it is alive.
+            (kind, &repr::Lvalue::Projection(box repr::Projection {
+                ref base,
+                elem: repr::ProjectionElem::Index(ref idx),
+            })) => {
+                write!(f, "function(b,i){{return{{get:function(){{return b[i]}}")?;
+                if kind != repr::BorrowKind::Shared {
+                    write!(f, ",set:function(x){{b[i]=x}}")?;
+                }
+                write!(f, "}}}}({},{})", LvalueGet(base), Operand(idx))
+            },
+            // Immutable references.
+            (repr::BorrowKind::Shared, lvalue) => write!(f, "{{get: function(){{return {}}}}}", LvalueGet(lvalue)),
+            // Mutable references. The setter assigns through the lvalue, rather than to its getter
+            // expression, as that isn't assignable for dereferences (as in reborrows).
+            (_, lvalue) =>
+                write!(f, "{{get:function(){{return {}}},set:function(x){{{}}}}}",
+                       LvalueGet(lvalue), LvalueSet(lvalue, Expr::Raw("x"))),
+        }
+    }
+}
+
+/// The JavaScript allocating an empty box, which the boxed value is then assigned through.
+const BOX: &'static str = "__cyano_box()";
+
+/// An array of some number of copies of some operand.
+///
+/// Rust only allows repeating `Copy` values, but aggregates are JS objects, so each element still
+/// needs its own copy to avoid aliasing.
+pub struct Repeat<'a>(pub &'a repr::Operand<'a>, pub u64);
+
+impl<'a> fmt::Display for Repeat<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "__cyano_repeat({},{})", Operand(self.0), self.1)
+    }
+}
+
 pub struct Rvalue<'a>(pub &'a repr::Rvalue<'a>);
 
 impl<'a> fmt::Display for Rvalue<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.0 {
             &repr::Rvalue::Use(ref operand) => write!(f, "{}", Operand(operand)),
-            // JavaScript doesn't have first class pointers, however it is possible to emulate them
-            // through closures. The basic idea is to let a setter and getter closure capture the
-            // lvalue, and then access it as an alias through these methods. It's pretty hacky, but
-            // it works.
-
-            // Immutable references.
-            &repr::Rvalue::Ref(_, repr::BorrowKind::Shared, ref lvalue) =>
-                write!(f, "{{get: function(){{return {}}}}}", LvalueGet(lvalue)),
-            // Mutable references.
-            &repr::Rvalue::Ref(_, _, ref lvalue) =>
-                write!(f, "{{get:function(){{return {}}},set:function(x){{{0}=x}}}}",
-                       LvalueGet(lvalue)),
+            &repr::Rvalue::Ref(_, kind, ref lvalue) => write!(f, "{}", Ref(kind, lvalue)),
+            &repr::Rvalue::Repeat(ref x, ref count) =>
+                write!(f, "{}", Repeat(x, ConstInt::Usize(count.value).to_u64_unchecked())),
             &repr::Rvalue::Len(ref lvalue) => write!(f, "{}.length", LvalueGet(lvalue)),
             // FIXME: Here be hacks! JavaScript does coercions literally everywhere. We cross our
             // fingers and hope that these matches the corresponding casts in Rust. Tests shows
             // that they do "most of the time" (read: might not work at all).
             &repr::Rvalue::Cast(_, ref operand, _) => write!(f, "{}", Operand(operand)),
-            &repr::Rvalue::CheckedBinaryOp(binop, ref x, ref y) | &repr::Rvalue::BinaryOp(binop, ref x, ref y) =>
-                write!(f, "({}){}({})", Operand(x), binop_to_js(binop), Operand(y)),
+            &repr::Rvalue::BinaryOp(binop, ref x, ref y) => match fold(binop, x, y) {
+                Some(n) => write!(f, "{}", Int(n)),
+                None => write!(f, "({}){}({})", Operand(x), binop_to_js(binop), Operand(y)),
+            },
+            // The overflow flag depends on the type, so the compiler lowers these itself, and
+            // this is merely the unchecked fallback.
+            &repr::Rvalue::CheckedBinaryOp(binop, ref x, ref y) => match fold(binop, x, y) {
+                Some(n) => write!(f, "[{},false]", Int(n)),
+                None => write!(f, "[({}){}({}),false]", Operand(x), binop_to_js(binop), Operand(y)),
+            },
             &repr::Rvalue::UnaryOp(unop, ref x) =>
                 write!(f, "{}({})", unop_to_js(unop), Operand(x)),
-            &repr::Rvalue::Box(_) => write!(f, "new function(){{\
-                                                    this.get=function(){{return this.x}};\
-                                                    this.set=function(x){{this.x=x}}\
-                                                }}"),
+            &repr::Rvalue::Box(_) => write!(f, "{}", BOX),
             &repr::Rvalue::Aggregate(ref kind, ref args) =>
                 match kind {
-                    &repr::AggregateKind::Vec | &repr::AggregateKind::Tuple => {
+                    // Closure environments are tuples of the captured variables.
+                    &repr::AggregateKind::Vec | &repr::AggregateKind::Tuple | &repr::AggregateKind::Closure(..) => {
                         // Start the array delimiter.
                         write!(f, "[")?;
                         for i in args {
@@ -222,11 +430,11 @@ impl<'a> fmt::Display for Rvalue<'a> {
                     &repr::AggregateKind::Adt(ref def, variant, _) => {
                         let variant = &def.variants[variant];
                         // Write the discriminant field.
-                        write!(f, "{{d:{}", variant.disr_val.to_u64_unchecked())?;
+                        write!(f, "{{d:{}", Int(variant.disr_val))?;
 
                         // Write in all the fields in.
-                        for (field, cont) in variant.fields.iter().zip(args) {
-                            write!(f, ",{}:{}", Field(repr::Field::new(field.name.0 as usize)), Operand(cont))?;
+                        for (field, cont) in args.iter().enumerate() {
+                            write!(f, ",{}:{}", Field(repr::Field::new(field)), Operand(cont))?;
                         }
 
                         // End the object.
@@ -260,3 +468,171 @@ impl<'a> fmt::Display for Statement<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rustc::mir::repr::{self, BinOp};
+    use rustc_const_math::{ConstFloat, ConstInt};
+    use rustc_data_structures::indexed_vec::Idx;
+    use runtime;
+    use syntax::codemap::DUMMY_SP;
+
+    #[test]
+    fn test_float() {
+        assert_eq!(Float(ConstFloat::F64(2.5)).to_string(), "2.5");
+        assert_eq!(Float(ConstFloat::F32(0.5)).to_string(), "0.5");
+        assert_eq!(Float(ConstFloat::F64(-1.0 / 0.0)).to_string(), "-Infinity");
+        assert_eq!(Float(ConstFloat::F64(0.0 / 0.0)).to_string(), "NaN");
+    }
+
+    #[test]
+    fn test_fold() {
+        let n = fold_int(BinOp::Mul, ConstInt::I32(3), ConstInt::I32(4)).unwrap();
+        let n = fold_int(BinOp::Add, ConstInt::I32(2), n).unwrap();
+        assert_eq!(Int(n).to_string(), "14");
+        assert_eq!(Int(fold_int(BinOp::Div, ConstInt::I32(-7), ConstInt::I32(2)).unwrap()).to_string(), "-3");
+
+        // Overflows and zero divisors are left for the runtime checks.
+        assert!(fold_int(BinOp::Add, ConstInt::U8(255), ConstInt::U8(1)).is_none());
+        assert!(fold_int(BinOp::Rem, ConstInt::I32(1), ConstInt::I32(0)).is_none());
+        assert!(fold_int(BinOp::Lt, ConstInt::I32(1), ConstInt::I32(2)).is_none());
+    }
+
+    #[test]
+    fn test_indirect_call() {
+        // `let f: fn(i32, i32) -> i32 = ..; f(x, y)` calls the function pointer held by `v0`.
+        let (func, args) = (repr::Operand::Consume(repr::Lvalue::Var(repr::Var::new(0))),
+                            [repr::Operand::Consume(repr::Lvalue::Arg(repr::Arg::new(0))),
+                             repr::Operand::Consume(repr::Lvalue::Arg(repr::Arg::new(1)))]);
+        let dest = repr::Lvalue::Temp(repr::Temp::new(0));
+        let js = LvalueSet(&dest, Expr::Call(&func, &args)).to_string();
+        assert_eq!(js, "t0=v0(a0,a1)");
+        let js = format!("var v0=function(x,y){{return x*y}},a0=6,a1=7,t0;{};console.log(t0)", js);
+        assert_eq!(runtime::eval(&js), "42\n");
+        assert_eq!(Expr::Call(&func, &[]).to_string(), "v0()");
+    }
+
+    #[test]
+    fn test_elem_ref() {
+        // `let r = &mut v[i]; i = 2; *r = 9;` still writes `v[1]`.
+        let elem = repr::Lvalue::Projection(Box::new(repr::Projection {
+            base: repr::Lvalue::Var(repr::Var::new(0)),
+            elem: repr::ProjectionElem::Index(repr::Operand::Consume(repr::Lvalue::Arg(repr::Arg::new(0)))),
+        }));
+        let js = Ref(repr::BorrowKind::Mut, &elem).to_string();
+        assert_eq!(js, "function(b,i){return{get:function(){return b[i]},set:function(x){b[i]=x}}}(v0,a0)");
+        let js = format!("var v0=[1,2,3],a0=1,t0,t1;t0={};t1={};a0=2;t0.set(9);\
+                          console.log(JSON.stringify(v0),t0.get(),t1.get(),typeof t1.set)",
+                         js, Ref(repr::BorrowKind::Shared, &elem));
+        assert_eq!(runtime::eval(&js), "[1,9,3] 9 9 undefined\n");
+    }
+
+    #[test]
+    fn test_type_args() {
+        let suffix = |types: &[&str]| {
+            let types: Vec<_> = types.iter().map(|ty| ty.to_string()).collect();
+            TypeArgs(&types).to_string()
+        };
+
+        // Non-generic items keep their plain names.
+        assert_eq!(suffix(&[]), "");
+        // Every instance of `fn pair<A, B>` gets its own name, and so its own function.
+        let names = [suffix(&["u32", "f64"]), suffix(&["f64", "u32"]), suffix(&["u32", "u32"]),
+                     suffix(&["Option<u32>", "f64"])];
+        for (i, name) in names.iter().enumerate() {
+            assert!(name.starts_with('_') && name[1..].chars().all(|c| c.is_digit(16)));
+            assert!(names[i + 1..].iter().all(|other| other != name));
+        }
+        assert_eq!(suffix(&["u32", "f64"]), names[0]);
+    }
+
+    #[test]
+    fn test_reborrow() {
+        // `&mut *p` for `p: &mut u32` in `a0` assigns through `p`, rather than to `p.get()`.
+        let deref = repr::Lvalue::Projection(Box::new(repr::Projection {
+            base: repr::Lvalue::Arg(repr::Arg::new(0)),
+            elem: repr::ProjectionElem::Deref,
+        }));
+        let js = Ref(repr::BorrowKind::Mut, &deref).to_string();
+        assert_eq!(js, "{get:function(){return a0.get()},set:function(x){a0.set(x)}}");
+        let js = format!("var n=1,a0={{get:function(){{return n}},set:function(x){{n=x}}}},t0;t0={};t0.set(5);\
+                          console.log(n,t0.get())", js);
+        assert_eq!(runtime::eval(&js), "5 5\n");
+    }
+
+    #[test]
+    fn test_is_exact_name() {
+        assert!(is_exact_name("greet"));
+        assert!(is_exact_name("on_click"));
+        assert!(is_exact_name("$init"));
+
+        assert!(!is_exact_name(""));
+        assert!(!is_exact_name("1st"));
+        assert!(!is_exact_name("delete"));
+        assert!(!is_exact_name("__cyano_trap"));
+        assert!(!is_exact_name("add"));
+        assert!(!is_exact_name("d1_0"));
+        assert!(!is_exact_name("g"));
+
+        assert!(!is_exact_name("console"));
+        assert!(!is_exact_name("Math"));
+        assert!(!is_exact_name("undefined"));
+        assert!(!is_exact_name("globalThis"));
+        assert!(!is_exact_name("TextEncoder"));
+        assert!(is_exact_name("math"));
+    }
+
+    #[test]
+    fn test_nested_tuple() {
+        // The guard of `match t { (x, (y, _)) if x > y => .., .. }`, with the tuple fields
+        // rewritten to indices. The constant indices take the type context, so here, they're
+        // held by the arguments `a0` (0) and `a1` (1) instead.
+        let index = |base, i| repr::Lvalue::Projection(Box::new(repr::Projection {
+            base: base,
+            elem: repr::ProjectionElem::Index(repr::Operand::Consume(repr::Lvalue::Arg(repr::Arg::new(i)))),
+        }));
+        let t = repr::Lvalue::Var(repr::Var::new(0));
+        let guard = repr::Rvalue::BinaryOp(BinOp::Gt, repr::Operand::Consume(index(t.clone(), 0)),
+                                           repr::Operand::Consume(index(index(t, 1), 0)));
+        let statement = repr::Statement {
+            source_info: repr::SourceInfo {
+                span: DUMMY_SP,
+                scope: repr::ARGUMENT_VISIBILITY_SCOPE,
+            },
+            kind: repr::StatementKind::Assign(repr::Lvalue::Temp(repr::Temp::new(0)), guard),
+        };
+
+        let js = Statement(&statement).to_string();
+        assert_eq!(js, "t0=(v0[a0])>(v0[a1][a0])");
+        let js = format!("var a0=0,a1=1,v0,t0;v0=[5,[3,4]];{0};console.log(t0);v0=[3,[5,4]];{0};console.log(t0)", js);
+        assert_eq!(runtime::eval(&js), "true\nfalse\n");
+    }
+
+    #[test]
+    fn test_repeat() {
+        // `[p; 3]` for a `p: (u32, [u32; 1])` in `v0`, whose elements are mutated separately.
+        let x = repr::Operand::Consume(repr::Lvalue::Var(repr::Var::new(0)));
+        let js = Repeat(&x, 3).to_string();
+        assert_eq!(js, "__cyano_repeat(v0,3)");
+        let js = format!("var v0=[1,[2]],t0;t0={};t0[0][1][0]=9;t0[2][0]=4;\
+                          console.log(JSON.stringify([v0,t0]))", js);
+        assert_eq!(runtime::eval(&js), "[[1,[2]],[[1,[9]],[1,[2]],[4,[2]]]]\n");
+    }
+
+    #[test]
+    fn test_box() {
+        // `let b = Box::new(5);`, moved into `v0` through a function, and `*v0 += 1;` through the
+        // accessors, which are detached from the box. Each box holds its own value.
+        let deref = repr::Lvalue::Projection(Box::new(repr::Projection {
+            base: repr::Lvalue::Temp(repr::Temp::new(0)),
+            elem: repr::ProjectionElem::Deref,
+        }));
+        let init = LvalueSet(&deref, Expr::Raw("5")).to_string();
+        assert_eq!(init, "t0.set(5)");
+        let js = format!("var t0,t1,v0;t0={0};{1};t1={0};t1.set(1);v0=function(a0){{return a0}}(t0);\
+                          var get=v0.get,set=v0.set;set(get()+1);console.log(v0.get(),t0.get(),t1.get())", BOX, init);
+        assert_eq!(runtime::eval(&js), "6 6 1\n");
+    }
+}
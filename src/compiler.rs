@@ -2,38 +2,316 @@ use rustc::hir::def_id::{self, DefId};
 use rustc::middle::const_val::ConstVal;
 use rustc::mir::mir_map::MirMap;
 use rustc::mir::repr;
+use rustc::mir::tcx::LvalueTy;
+use rustc::mir::visit::{LvalueContext, MutVisitor, Visitor};
+use rustc::session::config::DebugInfoLevel;
+use rustc::traits;
+use rustc::ty::subst::{Subst, Substs};
+use rustc::ty::{self, TyCtxt};
+use rustc_const_math::ConstInt;
 use rustc_data_structures::indexed_vec::Idx;
-use std::{mem, fmt};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Write};
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::Path;
+use syntax::abi::Abi;
+use syntax::ast::{FloatTy, IntTy, UintTy};
+use syntax::attr;
+use syntax::codemap::{Span, DUMMY_SP};
 
+use cache::FnCache;
 use codegen;
 use cell::MoveCell;
+use config::{Config, Target};
+use error::{self, CodegenError};
+use hoist::ConstTable;
+use report::{Diagnostic, SizeReport};
+use {emit, jump, resolve, runtime};
+
+/// The JavaScript starting the anonymous environment of the program.
+///
+/// The function is parenthesized, as a statement starting with `function` is a declaration,
+/// which can't be called in place.
+const PROGRAM_START: &'static str = "(function(){";
+/// The JavaScript ending the anonymous environment of the program, and running it.
+const PROGRAM_END: &'static str = "})();";
+/// The JavaScript trapping when an unreachable block is entered.
+const UNREACHABLE: &'static str = "__cyano_trap(\"Basic block terminated with unreachable.\");";
+/// The JavaScript trapping when no case of an exhaustive switch matches the value.
+const NON_EXHAUSTIVE: &'static str = "__cyano_trap(\"Non-exhaustive switch.\");";
+/// The JavaScript trapping when an enum has a discriminant of none of its variants.
+const INVALID_DISCRIMINANT: &'static str = "__cyano_trap(\"Invalid enum discriminant.\");";
 
 pub struct Compiler<'a> {
-    out: MoveCell<Option<fmt::Formatter<'a>>>,
+    /// The output buffer.
+    out: MoveCell<Option<String>>,
+    tcx: TyCtxt<'a, 'a, 'a>,
     mir: MirMap<'a>,
-    delayed_fns: Vec<DefId>,
+    config: Config,
+    /// Items referenced by the compiled functions, which are yet to be written, along with their
+    /// type arguments.
+    delayed_fns: MoveCell<Vec<(DefId, &'a Substs<'a>)>>,
+    /// The sizes of the code written by the last `finish`.
+    report: MoveCell<SizeReport>,
+    /// The approximate lowerings of the last `finish`.
+    diagnostics: MoveCell<Vec<Diagnostic>>,
+    /// The locals moved out of somewhere in the function being written.
+    moved: MoveCell<Vec<repr::Lvalue<'a>>>,
+    /// The instance of the function being written.
+    instance: MoveCell<(DefId, &'a Substs<'a>)>,
+    /// The large literals, which are declared once for the whole program.
+    consts: MoveCell<ConstTable>,
+    /// The error, which stopped the compilation, if any.
+    ///
+    /// The writers only return `fmt::Error`, so the cause is kept here until `finish` returns it.
+    error: MoveCell<Option<CodegenError>>,
+    /// The compiled functions, which are reused by later compilations.
+    cache: MoveCell<FnCache>,
+    /// The instances scheduled so far, by name, for resolving the dependencies of cached functions.
+    instances: MoveCell<HashMap<String, (DefId, &'a Substs<'a>)>>,
+    /// The hash of the names and paths of the items, which every cached function depends on.
+    layout: MoveCell<u64>,
 }
 
 impl<'a> Compiler<'a> {
-    pub fn finish(mut self) -> fmt::Result {
+    /// Create a new compiler for the MIR of some crate.
+    pub fn new(tcx: TyCtxt<'a, 'a, 'a>, mir: MirMap<'a>, config: Config) -> Compiler<'a> {
+        Compiler {
+            out: MoveCell::new(Some(String::new())),
+            tcx: tcx,
+            mir: mir,
+            config: config,
+            delayed_fns: MoveCell::new(Vec::new()),
+            report: MoveCell::new(SizeReport::default()),
+            diagnostics: MoveCell::new(Vec::new()),
+            moved: MoveCell::new(Vec::new()),
+            instance: MoveCell::new((DefId::local(def_id::DefIndex::new(0)), Substs::empty(tcx))),
+            consts: MoveCell::new(ConstTable::default()),
+            error: MoveCell::new(None),
+            cache: MoveCell::new(FnCache::default()),
+            instances: MoveCell::new(HashMap::new()),
+            layout: MoveCell::new(0),
+        }
+    }
+
+    /// Compile the crate into JavaScript.
+    pub fn finish(&self) -> Result<String, CodegenError> {
+        // Start from a blank slate.
+        self.out.replace(Some(String::new()));
+        self.report.replace(SizeReport::default());
+        self.consts.replace(ConstTable::default());
+        self.diagnostics.replace(Vec::new());
+        self.error.replace(None);
+        self.instances.replace(HashMap::new());
+
+        if let Err(err) = self.write_program() {
+            return Err(self.error.replace(None).unwrap_or(CodegenError::Fmt(err)));
+        }
+
+        let out = self.out.replace(Some(String::new())).unwrap();
+        // Everything not accounted for by a function is overhead.
+        let mut report = self.report.replace(SizeReport::default());
+        report.overhead = out.len() - report.total();
+        self.report.replace(report);
+
+        Ok(out)
+    }
+
+    /// Compile the crate into a JavaScript file at `path`.
+    pub fn finish_to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let js = self.finish().map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+
+        emit::write_js(path, &js)
+    }
+
+    /// Compile the crate into an HTML page at `path`, which runs the program when opened.
+    ///
+    /// This is meant for quick testing and demos; the page holds nothing but the script.
+    pub fn finish_to_html<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let js = self.finish().map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+
+        emit::write_js(path, &emit::html(&js))
+    }
+
+    /// Get the sizes of the code generated by the last call to `finish`.
+    ///
+    /// The sizes add up to the length of the output.
+    pub fn size_report(&self) -> SizeReport {
+        let report = self.report.replace(SizeReport::default());
+        self.report.replace(report.clone());
+
+        report
+    }
+
+    /// Get the constructs, which the last call to `finish` lowered approximately.
+    ///
+    /// These are the places, where the semantics of the output may diverge from native Rust.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let diagnostics = self.diagnostics.replace(Vec::new());
+        self.diagnostics.replace(diagnostics.clone());
+
+        diagnostics
+    }
+
+    /// Seed the cache of compiled functions, e.g. with one loaded by `FnCache::parse`.
+    ///
+    /// Functions, which haven't changed since they were cached, are reused by `finish` rather than
+    /// compiled again. Their diagnostics aren't reported again.
+    pub fn seed_cache(&self, cache: FnCache) {
+        self.cache.replace(cache);
+    }
+
+    /// Get the cache of compiled functions, including the functions compiled by the last call to
+    /// `finish`.
+    ///
+    /// This can be persisted with `FnCache::serialize` and passed to `seed_cache` of the next
+    /// compilation.
+    pub fn fn_cache(&self) -> FnCache {
+        let cache = self.cache.replace(FnCache::default());
+        self.cache.replace(cache.clone());
+
+        cache
+    }
+
+    /// Stop the compilation with the error `err`.
+    fn fail<T>(&self, err: CodegenError) -> Result<T, fmt::Error> {
+        self.error.replace(Some(err));
+
+        Err(fmt::Error)
+    }
+
+    /// Record that the construct at `span` is lowered approximately.
+    fn diagnose(&self, span: Span, message: &'static str) {
+        let mut diagnostics = self.diagnostics.replace(Vec::new());
+        diagnostics.push(Diagnostic {
+            span: span,
+            message: message,
+        });
+        self.diagnostics.replace(diagnostics);
+    }
+
+    /// Record the approximations made when lowering `rvalue`, located at `span`.
+    fn diagnose_rvalue(&self, body: &repr::Mir<'a>, span: Span, rvalue: &repr::Rvalue<'a>) {
+        match rvalue {
+            &repr::Rvalue::Cast(repr::CastKind::Misc, ref x, target) => {
+                let source = self.operand_ty(body, x);
+                if target.is_integral() && source.is_floating_point() {
+                    self.diagnose(span, "float to integer casts are emitted as is, so they don't truncate");
+                } else if target.is_integral() && source.is_integral() && source != target {
+                    self.diagnose(span, "integer casts are emitted as is, so narrowing casts don't wrap");
+                }
+            },
+            &repr::Rvalue::BinaryOp(binop, ref x, _)
+                if (binop == repr::BinOp::BitAnd || binop == repr::BinOp::BitOr || binop == repr::BinOp::BitXor)
+                    && self.operand_ty(body, x).is_bool() =>
+                self.diagnose(span, "bitwise operations on bools give integers"),
+            _ => (),
+        }
+    }
+
+    fn write_program(&self) -> fmt::Result {
         // Start anonymous environment.
-        self.out(|f| write!(f, "function(){{d0_0();"))?;
+        self.out(|f| write!(f, "{}", PROGRAM_START))?;
+
+        // Declare the object exports are assigned to.
+        let ns = namespace(self.config.target, self.config.namespace.as_ref().map(|ns| &ns[..]));
+        self.out(|f| write!(f, "{}", ns))?;
+
+        // The prelude goes first, so the program can use what it declares.
+        if let Some(ref prelude) = self.config.prelude {
+            self.out(|f| write!(f, "{}", prelude))?;
+        }
+
+        // The names of items are derived from their `DefId`s, so cached functions are only valid
+        // as long as the ids refer to the same items.
+        let mut hasher = DefaultHasher::new();
+        let mut ids: Vec<_> = self.mir.map.keys().cloned().collect();
+        ids.sort();
+        for id in ids {
+            (id.index.as_u32(), self.tcx.item_path_str(id)).hash(&mut hasher);
+        }
+        for cnum in self.tcx.sess.cstore.crates() {
+            (cnum, self.tcx.sess.cstore.crate_name(cnum).to_string()).hash(&mut hasher);
+        }
+        self.layout.replace(hasher.finish());
+
+        // Count the literals of the crate, so the repeated ones are shared.
+        let mut consts = ConstTable::default();
+        for body in self.mir.map.values() {
+            let mut collector = ItemCollector {
+                tcx: self.tcx,
+                items: Vec::new(),
+                literals: Vec::new(),
+            };
+            collector.visit_mir(body);
+            consts.count(&collector.literals);
+        }
+        self.consts.replace(consts);
+
+        // The constants go first, as they are used from the start.
+        let consts_pos = self.len();
+
+        // Declare the runtime helpers. Those declared by the prelude are replaced, as the later
+        // of two function declarations takes effect.
+        for i in runtime::helpers(self.config.target) {
+            if self.config.prelude.as_ref().map_or(false, |prelude| runtime::declares(prelude, i)) {
+                continue;
+            }
+            self.out(|f| write!(f, "{}", i))?;
+        }
+
+        let entry = (DefId::local(def_id::DefIndex::new(0)), Substs::empty(self.tcx));
+        self.write_item(entry)?;
+
+        // Write every referenced item until no new ones show up. The visited items are
+        // remembered, so cycles (e.g. recursion) only get written once.
+        let mut visited = HashSet::new();
+        visited.insert(entry);
+        loop {
+            let delayed_fns = self.delayed_fns.replace(Vec::new());
+            if delayed_fns.is_empty() {
+                break;
+            }
+
+            for i in delayed_fns {
+                if visited.insert(i) {
+                    self.write_item(i)?;
+                }
+            }
+        }
 
-        self.write_fn(DefId::local(def_id::DefIndex::new(0)))?;
+        // Declare the constants hoisted from the functions.
+        let consts = self.consts.replace(ConstTable::default()).declarations();
+        self.out(|f| {
+            let rest = f[consts_pos..].to_string();
+            f.truncate(consts_pos);
+            f.push_str(&consts);
+            f.push_str(&rest);
+            Ok(())
+        })?;
 
-        // FIXME: In some cases, this might loop infinitely due to visiting the same functions in
-        // cycle. The result should be cachced and returned on second visit.
-        let delayed_fns = mem::replace(&mut self.delayed_fns, Vec::new());
-        for i in delayed_fns {
-            self.write_fn(i)?;
+        // Expose the entry point for running the program again. This goes before the call, so it
+        // is set even if the first run panics.
+        if let Some(ref name) = self.config.export_main {
+            self.out(|f| write!(f, "__cyano_ns.{}=d0_0;", name))?;
+        }
+
+        // Run the program. This comes after every declaration, so it doesn't rely on the hoisting
+        // of function declarations, and the helpers' state is initialized.
+        if self.config.target == Target::Worker && self.config.worker_on_message {
+            self.out(|f| write!(f, "var __cyano_message;self.onmessage=function(e){{__cyano_message=e.data;d0_0()}};"))?;
+        } else {
+            self.out(|f| write!(f, "d0_0();"))?;
         }
 
         // End anonymous environment.
-        self.out(|f| write!(f, "}}()"))
+        self.out(|f| write!(f, "{}", PROGRAM_END))
     }
 
-    fn out<F: FnOnce(&mut fmt::Formatter) -> fmt::Result>(&self, f: F) -> fmt::Result {
-        // Temporarily grab the formatter.
+    fn out<F: FnOnce(&mut String) -> fmt::Result>(&self, f: F) -> fmt::Result {
+        // Temporarily grab the buffer.
         let mut old = self.out.replace(None).unwrap();
         // Run the closure.
         let res = f(&mut old);
@@ -43,66 +321,1004 @@ impl<'a> Compiler<'a> {
         res
     }
 
-    fn write_fn(&self, id: DefId) -> fmt::Result {
-        self.out(|f| write!(f, "function {}(", codegen::Item(id)))?;
+    /// Schedule the instance of the item `id` with type arguments `substs` for being written.
+    fn schedule(&self, id: DefId, substs: &'a Substs<'a>) {
+        let mut delayed_fns = self.delayed_fns.replace(Vec::new());
+        delayed_fns.push((id, substs));
+        self.delayed_fns.replace(delayed_fns);
+
+        let mut instances = self.instances.replace(HashMap::new());
+        instances.insert(codegen::Instance(id, substs).to_string(), (id, substs));
+        self.instances.replace(instances);
+    }
+
+    /// Get the instance named `name`, if it is known.
+    ///
+    /// Instances without type arguments are named by their `DefId` alone, whereas generic ones are
+    /// only known once they have been scheduled.
+    fn instance_by_name(&self, name: &str) -> Option<(DefId, &'a Substs<'a>)> {
+        let instances = self.instances.replace(HashMap::new());
+        let instance = instances.get(name).cloned();
+        self.instances.replace(instances);
+
+        if instance.is_some() || !name.starts_with('d') {
+            return instance;
+        }
+
+        let mut parts = name[1..].split('_').map(|x| u32::from_str_radix(x, 16));
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(index)), Some(Ok(krate)), None) => Some((DefId {
+                krate: krate,
+                index: def_id::DefIndex::new(index as usize),
+            }, Substs::empty(self.tcx))),
+            _ => None,
+        }
+    }
+
+    /// Hash the inputs, which the JavaScript of the instance `(id, substs)` is compiled from.
+    ///
+    /// Besides the MIR of the function and the configuration, this covers the MIR of its local
+    /// callees, as trivial ones are inlined.
+    fn fn_hash(&self, id: DefId, substs: &'a Substs<'a>) -> u64 {
+        let body = &self.mir.map[&id];
+        let layout = self.layout.replace(0);
+        self.layout.replace(layout);
+
+        let mut hasher = DefaultHasher::new();
+        layout.hash(&mut hasher);
+        format!("{:?}", self.config).hash(&mut hasher);
+        format!("{:?}", substs).hash(&mut hasher);
+        format!("{:?}", body).hash(&mut hasher);
+        for bb in body.basic_blocks() {
+            if let repr::TerminatorKind::Call {
+                func: repr::Operand::Constant(repr::Constant { literal: repr::Literal::Item { def_id, .. }, .. }),
+                ..
+            } = bb.terminator().kind {
+                if let Some(callee) = self.mir.map.get(&def_id) {
+                    format!("{:?}", callee).hash(&mut hasher);
+                }
+            }
+        }
+
+        hasher.finish()
+    }
+
+    /// Get the length of the output written so far.
+    fn len(&self) -> usize {
+        let mut len = 0;
+        // This closure never fails.
+        let _ = self.out(|f| {
+            len = f.len();
+            Ok(())
+        });
+
+        len
+    }
+
+    /// Write the instance `(id, substs)` of an item, given that it is something that can be
+    /// written.
+    fn write_item(&self, (id, substs): (DefId, &'a Substs<'a>)) -> fmt::Result {
+        let start = self.len();
+
+        if self.mir.map.contains_key(&id) {
+            self.write_fn(id, substs)?;
+        } else if let Some(variant) = self.ctor_variant(id) {
+            self.write_ctor(id, substs, variant)?;
+        } else {
+            // Intrinsics are lowered at their call sites, and items from other crates are not
+            // compiled here.
+            return Ok(());
+        }
+
+        // Record the size of the item.
+        let mut report = self.report.replace(SizeReport::default());
+        report.fns.push((id, self.len() - start));
+        self.report.replace(report);
+
+        Ok(())
+    }
+
+    /// Get the enum variant of the tuple variant constructor `id`, if it is one.
+    fn ctor_variant(&self, id: DefId) -> Option<ty::VariantDef<'a>> {
+        match self.tcx.lookup_item_type(id).ty.sty {
+            ty::TyFnDef(_, _, fty) => match fty.sig.skip_binder().output.sty {
+                ty::TyEnum(adt, _) => adt.variants.iter().find(|variant| variant.did == id),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Write a constructor function for the tuple variant `variant`.
+    ///
+    /// Constructors have no MIR, but they can be used as function values (e.g. `map(Some)`), so
+    /// we synthesize a function building the aggregate.
+    fn write_ctor(&self, id: DefId, substs: &'a Substs<'a>, variant: ty::VariantDef<'a>) -> fmt::Result {
+        let ctor = ctor(&codegen::Instance(id, substs).to_string(), &codegen::Int(variant.disr_val).to_string(),
+                        variant.fields.len());
+        self.out(|f| write!(f, "{}", ctor))
+    }
+
+    /// Get the JavaScript name of the instance `(id, substs)`.
+    ///
+    /// `#[no_mangle]` functions keep their exact name, so they can be called from other
+    /// JavaScript, unless that name can't be used as is.
+    fn name(&self, id: DefId, substs: &'a Substs<'a>) -> String {
+        if self.exact_name(id) {
+            self.tcx.item_name(id).as_str().to_string()
+        } else {
+            codegen::Instance(id, substs).to_string()
+        }
+    }
+
+    /// Check if the item `id` is `#[no_mangle]` and its name is usable in JavaScript.
+    fn exact_name(&self, id: DefId) -> bool {
+        attr::contains_name(&self.tcx.get_attrs(id), "no_mangle")
+            && codegen::is_exact_name(&self.tcx.item_name(id).as_str())
+    }
+
+    /// Write the function instance `(id, substs)`, reusing it from the cache if it is unchanged.
+    fn write_fn(&self, id: DefId, substs: &'a Substs<'a>) -> fmt::Result {
+        let start = self.len();
+        let body = &self.mir.map[&id];
+        let key = codegen::Instance(id, substs).to_string();
+        let hash = self.fn_hash(id, substs);
+
+        // The cached function can only be used, if the items it refers to can still be scheduled.
+        let cache = self.cache.replace(FnCache::default());
+        let cached = cache.reuse(&key, hash, |dep| self.instance_by_name(dep));
+        self.cache.replace(cache);
+
+        if let Some((js, deps)) = cached {
+            self.out(|f| {
+                f.push_str(&js);
+                Ok(())
+            })?;
+            for (id, substs) in deps {
+                self.schedule(id, substs);
+            }
+        } else {
+            let scheduled = self.delayed_fns.replace(Vec::new());
+            let deps_start = scheduled.len();
+            self.delayed_fns.replace(scheduled);
+
+            self.write_fn_body(id, substs)?;
+
+            let scheduled = self.delayed_fns.replace(Vec::new());
+            let deps = scheduled[deps_start..].iter()
+                .map(|&(id, substs)| codegen::Instance(id, substs).to_string())
+                .collect();
+            self.delayed_fns.replace(scheduled);
+
+            let mut js = String::new();
+            self.out(|f| {
+                js.push_str(&f[start..]);
+                Ok(())
+            })?;
+            let mut cache = self.cache.replace(FnCache::default());
+            cache.insert(key, hash, js, deps);
+            self.cache.replace(cache);
+        }
+
+        // Schedule the items the function refers to.
+        let mut collector = ItemCollector {
+            tcx: self.tcx,
+            items: Vec::new(),
+            literals: Vec::new(),
+        };
+        collector.visit_mir(body);
+        let mut delayed_fns = self.delayed_fns.replace(Vec::new());
+        delayed_fns.extend(collector.items);
+        self.delayed_fns.replace(delayed_fns);
+
+        // Refer to the large literals by their constants.
+        let mut consts = self.consts.replace(ConstTable::default());
+        self.out(|f| {
+            let js = consts.hoist(&f[start..], &collector.literals);
+            f.truncate(start);
+            f.push_str(&js);
+            Ok(())
+        })?;
+        self.consts.replace(consts);
+
+        Ok(())
+    }
+
+    /// Compile the body of the function instance `(id, substs)`.
+    fn write_fn_body(&self, id: DefId, substs: &'a Substs<'a>) -> fmt::Result {
+        let name = self.name(id, substs);
+        self.out(|f| write!(f, "function {}(", name))?;
+        self.instance.replace((id, substs));
 
         // Declare the arguments.
         for (arg, _) in self.mir.map[&id].arg_decls.iter_enumerated() {
-            self.out(|f| write!(f, "{}", codegen::Arg(arg)))?;
+            self.out(|f| write!(f, "{}{}", if arg.index() == 0 { "" } else { "," }, codegen::Arg(arg)))?;
+        }
+
+        self.out(|f| write!(f, "){{"))?;
+
+        if self.config.asmjs {
+            // Annotate the types of the numeric arguments up front.
+            for (arg, decl) in self.mir.map[&id].arg_decls.iter_enumerated() {
+                let ty = decl.ty.subst(self.tcx, substs);
+                let js = codegen::Arg(arg).to_string();
+                let coerced = coerce(ty, &js);
+                if coerced != js {
+                    self.out(|f| write!(f, "{}={};", js, coerced))?;
+                }
+            }
+        }
+
+        if self.config.trace {
+            // Break on entry, so the function boundary is visible when stepping through.
+            self.out(|f| write!(f, "debugger;"))?;
+        }
+
+        let body = &self.mir.map[&id];
+
+        // Unimplemented stuff. Report it by the span of the function rather than panicking, so the
+        // user knows which function to avoid.
+        if !body.promoted.is_empty() {
+            return self.fail(CodegenError::UnsupportedInFn {
+                feature: "promoted rvalues",
+                item: self.tcx.item_path_str(id),
+                span: body.span,
+            });
         }
 
+        // Declare the locals up front, as the blocks can run in any order. In particular, this
+        // keeps `let` locals out of their temporal dead zone.
+        self.out(|f| write!(f, "{}", local_decls(self.config.let_declarations, body.var_decls.len(),
+                                                  body.temp_decls.len())))?;
+
         // We initialize our "goto loop", which is a jump table used to emulate gotos in
         // JavaScript. While it might seem slow at first, it is worth noting that every modern JS
         // engine will optimize this down to gotos making it zero-cost. Even without such an
         // optimization, the performance is still OK (when the cases in a switch statements is
         // above some threshold, it will almost always be transformed to a jump table, which means
         // one lookup per goto).
-        self.out(|f| write!(f, "){{var g=0;t:while(true){{switch g{{"))?;
+        self.out(|f| write!(f, "t:while(true){{switch(g){{"))?;
 
-        let body = &self.mir.map[&id];
+        let mut moves = MoveCollector {
+            moved: Vec::new(),
+            moves: |lvalue: &repr::Lvalue<'a>| self.moves_by_default(body, lvalue),
+        };
+        moves.visit_mir(body);
+        self.moved.replace(moves.moved);
+
+        for (id, bb) in body.basic_blocks().iter_enumerated() {
+            self.out(|f| write!(f, "case {}:", id.index()))?;
+            // FIXME: I'm sure there is a way to avoid this clone.
+            let mut bb = bb.clone();
+            TupleFields {
+                tcx: self.tcx,
+                body: body,
+            }.visit_basic_block_data(id, &mut bb);
+            self.write_bb(body, bb)?;
+            self.out(|f| write!(f, "break;"))?;
+        }
 
-        // Unimplemented stuff.
-        assert!(body.promoted.is_empty(), "Promoted rvalues are unimplemented.");
-        assert!(body.upvar_decls.is_empty(), "Upvars are unimplemented.");
+        // End the goto loop and the function body.
+        self.out(|f| write!(f, "}}}}}}"))?;
 
-        // The return variable.
-        self.out(|f| write!(f, "var r"))?;
+        if self.exact_name(id) {
+            // Function pointers still refer to the mangled name.
+            self.out(|f| write!(f, "function {}(){{return {}.apply(null,arguments)}}",
+                                codegen::Instance(id, substs), name))?;
+        } else if attr::contains_name(&self.tcx.get_attrs(id), "no_mangle") {
+            self.diagnose(body.span, "the `#[no_mangle]` name isn't usable in JavaScript, so it is mangled");
+        }
+
+        Ok(())
+    }
+
+    fn goto(&self, bb: repr::BasicBlock) -> fmt::Result {
+        self.out(|f| write!(f, "g={};continue t;", bb.index()))
+    }
+
+    /// Write an assignment of the JavaScript expression `js` to `lvalue`.
+    fn write_assign(&self, lvalue: &repr::Lvalue<'a>, js: &str) -> fmt::Result {
+        self.out(|f| write!(f, "{};", codegen::LvalueSet(lvalue, codegen::Expr::Raw(js))))
+    }
+
+    /// Get the lvalue read by the trivial accessor `id`, if it is one.
+    ///
+    /// Trivial accessors consist of a single block, which assigns a field or dereference path of
+    /// an argument to the return pointer. As such, they have no side effects and can't recurse.
+    fn trivial_accessor(&self, id: DefId) -> Option<&repr::Lvalue<'a>> {
+        let body = match self.mir.map.get(&id) {
+            Some(body) if body.basic_blocks().len() == 1 => body,
+            _ => return None,
+        };
+        let bb = &body.basic_blocks()[repr::START_BLOCK];
 
-        // Declare the variables.
-        for (var, _) in body.var_decls.iter_enumerated() {
-            self.out(|f| write!(f, ",{}", codegen::Var(var)))?;
+        match bb.terminator().kind {
+            repr::TerminatorKind::Return => (),
+            _ => return None,
         }
 
-        // Declare the variables.
-        for (var, _) in body.temp_decls.iter_enumerated() {
-            self.out(|f| write!(f, "{}", codegen::Tmp(var)))?;
+        let mut read = None;
+        for i in &bb.statements {
+            match i.kind {
+                repr::StatementKind::StorageLive(_) | repr::StatementKind::StorageDead(_) => (),
+                repr::StatementKind::Assign(repr::Lvalue::ReturnPointer,
+                                            repr::Rvalue::Use(repr::Operand::Consume(ref lvalue)))
+                    if read.is_none() && is_arg_path(lvalue) => read = Some(lvalue),
+                _ => return None,
+            }
         }
 
-        self.out(|f| write!(f, ";"))?;
+        read
+    }
 
-        for (id, bb) in body.basic_blocks().iter_enumerated() {
-            self.out(|f| write!(f, "case {}:", id.index()))?;
-            // FIXME: I'm sure there is a way to avoid this clone.
-            self.write_bb(bb.clone())?;
-            self.out(|f| write!(f, "break;"))?;
+    /// Get the lvalue, which the call of `callee` with `args` can be inlined to, if any.
+    fn inline_call(&self, callee: DefId, args: &[repr::Operand<'a>]) -> Option<repr::Lvalue<'a>> {
+        if !self.config.inline {
+            return None;
         }
 
-        // End the function body.
-        self.out(|f| write!(f, "}}"))
+        self.trivial_accessor(callee).and_then(|lvalue| subst_args(lvalue, args))
     }
 
-    fn goto(&self, bb: repr::BasicBlock) -> fmt::Result {
-        self.out(|f| write!(f, "g={};continue t;", bb.index()))
+    /// Write a call to the library item `id`, if it is overridden by the runtime.
+    ///
+    /// Items from other crates have no MIR available, so the commonly used ones, which are
+    /// implemented in terms of raw pointers, are replaced by JS-friendly versions. `substs` are
+    /// the type arguments of the call. Returns `false` if `id` isn't overridden.
+    fn write_override(&self, id: DefId, substs: &'a Substs<'a>, dest: &repr::Lvalue<'a>,
+                      args: &[repr::Operand<'a>]) -> Result<bool, fmt::Error> {
+        let path = self.tcx.item_path_str(id);
+        let self_ty = substs.self_ty();
+        let self_path = match self_ty.map(|ty| &ty.sty) {
+            Some(&ty::TyStruct(adt, _)) => Some(self.tcx.item_path_str(adt.did)),
+            Some(&ty::TyStr) => Some("str".to_string()),
+            Some(&ty::TyRef(_, ty::TypeAndMut { ty, .. })) if ty.sty == ty::TyStr => Some("&str".to_string()),
+            _ => None,
+        };
+
+        // The integer type of inherent integer methods (e.g. `u32::to_be`).
+        let int_method = self.tcx.impl_of_method(id)
+            .map(|impl_id| self.tcx.lookup_item_type(impl_id).ty)
+            .and_then(|ty| int_bits(ty).map(|_| ty));
+
+        // The operands of string comparisons. `&str` is represented by the JS string itself, so
+        // only comparisons of `&str`s (taking `&&str`) need a dereference.
+        let strs = match self_path.as_ref().map(|x| &**x) {
+            Some("str") => Some((codegen::Operand(&args[0]).to_string(), codegen::Operand(&args[1]).to_string())),
+            Some("&str") => Some((format!("{}.get()", codegen::Operand(&args[0])),
+                                  format!("{}.get()", codegen::Operand(&args[1])))),
+            _ => None,
+        };
+
+        let accessor = if path.starts_with("core::slice::SliceExt::") {
+            let args: Vec<_> = args.iter().map(|x| codegen::Operand(x).to_string()).collect();
+            slice_accessor(&path["core::slice::SliceExt::".len()..], &args)
+        } else {
+            None
+        };
+
+        match (&*path, self_path.as_ref().map(|x| &**x)) {
+            // Iterating over slices.
+            ("core::slice::SliceExt::iter", _) | ("core::slice::SliceExt::iter_mut", _) =>
+                self.write_assign(dest, &slice_iter(&codegen::Operand(&args[0]).to_string()))?,
+            ("core::iter::IntoIterator::into_iter", _) if self_ty.map_or(false, is_slice_ref) =>
+                self.write_assign(dest, &slice_iter(&codegen::Operand(&args[0]).to_string()))?,
+            ("core::iter::Iterator::next", Some("core::slice::Iter"))
+            | ("core::iter::Iterator::next", Some("core::slice::IterMut")) =>
+                self.write_assign(dest, &slice_iter_next(&codegen::Operand(&args[0]).to_string()))?,
+            // The reflexive conversion, which `?` uses when the error types match, is the
+            // identity. Other `From` impls are resolved like any other trait method.
+            ("core::convert::From::from", _) if substs.type_at(0) == substs.type_at(1) =>
+                self.write_assign(dest, &codegen::Operand(&args[0]).to_string())?,
+            // Cloning slices. `copy_from_slice` is the same, except the elements are `Copy`,
+            // but these still need a deep copy if they're aggregates.
+            ("core::slice::SliceExt::clone_from_slice", _) | ("core::slice::SliceExt::copy_from_slice", _) => {
+                let scalar = match self_ty.map(|ty| &ty.sty) {
+                    Some(&ty::TySlice(elem)) => elem.is_scalar(),
+                    _ => false,
+                };
+                self.out(|f| write!(f, "{}", clone_from_slice(&codegen::Operand(&args[0]).to_string(),
+                                                              &codegen::Operand(&args[1]).to_string(), scalar)))?
+            },
+            // The length and elements of slices (e.g. slice arguments), which libcore reads through
+            // their raw representation.
+            _ if accessor.is_some() => self.write_assign(dest, &accessor.unwrap())?,
+            // Splitting slices, which gives views over the backing array.
+            ("core::slice::SliceExt::split_at", _) | ("core::slice::SliceExt::split_at_mut", _) =>
+                self.write_assign(dest, &split_at(&codegen::Operand(&args[0]).to_string(),
+                                                  &codegen::Operand(&args[1]).to_string()))?,
+            // Byte order conversions of integers. Numbers have no byte order in JavaScript, so the
+            // logical value is taken to be little-endian, which only big-endian conversions swap.
+            (path, _) if path.starts_with("core::num::<impl ") && int_method.is_some() => {
+                let ty = int_method.unwrap();
+                match &*self.tcx.item_name(id).as_str() {
+                    "to_le" | "from_le" => self.write_assign(dest, &codegen::Operand(&args[0]).to_string())?,
+                    "to_be" | "from_be" | "swap_bytes" =>
+                        self.write_assign(dest, &bswap(ty, &codegen::Operand(&args[0]).to_string()))?,
+                    _ => return Ok(false),
+                }
+            },
+            // The sign of floats.
+            ("core::num::Float::signum", _) =>
+                self.write_assign(dest, &signum(&codegen::Operand(&args[0]).to_string()))?,
+            // Indexing slices (and arrays) by ranges, e.g. `&s[a..b]`, which gives a view over
+            // the backing array.
+            ("core::ops::Index::index", _) | ("core::ops::IndexMut::index_mut", _)
+                if self_ty.map_or(false, |ty| match ty.sty {
+                    ty::TySlice(_) | ty::TyArray(..) => true,
+                    _ => false,
+                }) => {
+                let index = match substs.type_at(1).sty {
+                    ty::TyStruct(adt, _) => slice_index(&self.tcx.item_path_str(adt.did), &codegen::Operand(&args[0]).to_string(),
+                                                        &codegen::Operand(&args[1]).to_string()),
+                    _ => None,
+                };
+                match index {
+                    Some(index) => self.write_assign(dest, &index)?,
+                    None => return Ok(false),
+                }
+            },
+            // Binary searching with a comparison function. Closures are called with their
+            // environment by reference, as the bound is `FnMut`, whereas other functions are JS
+            // functions already.
+            ("core::slice::SliceExt::binary_search_by", _) => {
+                let cmp = match substs.type_at(1).sty {
+                    ty::TyClosure(closure, _) => {
+                        self.schedule(closure, Substs::empty(self.tcx));
+                        fn_mut_closure(&codegen::Item(closure).to_string(), &codegen::Operand(&args[1]).to_string())
+                    },
+                    _ => codegen::Operand(&args[1]).to_string(),
+                };
+                self.write_assign(dest, &format!("__cyano_binary_search({},{})", codegen::Operand(&args[0]), cmp))?
+            },
+            // Matching on string literals compares through the `str_eq` lang item. Strings are
+            // primitive in JS, so `===` compares them by value.
+            ("core::str::eq_slice", _) =>
+                self.write_assign(dest, &format!("{}==={}", codegen::Operand(&args[0]), codegen::Operand(&args[1])))?,
+            // Iterating over ranges. Ranges are their own iterators.
+            ("core::iter::IntoIterator::into_iter", Some("core::ops::Range")) =>
+                self.write_assign(dest, &codegen::Operand(&args[0]).to_string())?,
+            ("core::iter::Iterator::next", Some("core::ops::Range")) =>
+                self.write_assign(dest, &range_next(&codegen::Operand(&args[0]).to_string()))?,
+            // Comparing strings. The JS relational operators compare UTF-16 code units, which
+            // doesn't match Rust's byte order, so a runtime helper is used.
+            (path, _) if strs.is_some() => {
+                let (a, b) = strs.unwrap();
+                match str_cmp(path, &a, &b) {
+                    Some(cmp) => self.write_assign(dest, &cmp)?,
+                    None => return Ok(false),
+                }
+            },
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Write a call to `Clone::clone` (`id`) for the `Self` type of `substs`.
+    ///
+    /// Impls we have the MIR of (e.g. derived ones) are called directly, so fields with custom
+    /// clones still go through them. Anything else (such as the library collections) is deep
+    /// copied by the runtime, as plain assignment would alias the aggregates.
+    fn write_clone(&self, id: DefId, substs: &'a Substs<'a>, dest: &repr::Lvalue<'a>,
+                   args: &[repr::Operand<'a>]) -> fmt::Result {
+        match resolve::impl_method(self.tcx, id, substs) {
+            Some((method, substs)) if self.mir.map.contains_key(&method) => {
+                self.schedule(method, substs);
+                self.write_assign(dest, &format!("{}({})", codegen::Instance(method, substs),
+                                                 codegen::Operand(&args[0])))
+            },
+            _ => self.write_assign(dest, &format!("__cyano_dup({}.get())", codegen::Operand(&args[0]))),
+        }
+    }
+
+    /// Substitute the type arguments of the function being written into `substs`.
+    fn subst(&self, substs: &'a Substs<'a>) -> &'a Substs<'a> {
+        substs.subst(self.tcx, self.current().1)
+    }
+
+    /// Get the instance of the function being written.
+    fn current(&self) -> (DefId, &'a Substs<'a>) {
+        let current = self.instance.replace((DefId::local(def_id::DefIndex::new(0)), Substs::empty(self.tcx)));
+        self.instance.replace(current);
+
+        current
+    }
+
+    /// Get the instance called by a call of the item `id` with the type arguments `substs`.
+    ///
+    /// Trait methods are resolved statically to the instance of their implementation, if it can
+    /// be selected. Otherwise, the call is left as is.
+    fn callee(&self, id: DefId, substs: &'a Substs<'a>) -> (DefId, &'a Substs<'a>) {
+        resolve::impl_method(self.tcx, id, substs).unwrap_or((id, substs))
+    }
+
+    /// Get the JavaScript calling the closure `closure` through the `Fn*` trait method `method`.
+    ///
+    /// The trait methods take the arguments as a tuple, whereas the closure takes them separately.
+    fn closure_call(&self, body: &repr::Mir<'a>, method: DefId, closure: DefId,
+                    args: &[repr::Operand<'a>]) -> String {
+        let mut env = codegen::Operand(&args[0]).to_string();
+        // `call_once` takes the environment by value, but unless the closure is `FnOnce`, it
+        // takes it by reference.
+        if self.tcx.trait_of_item(method) == self.tcx.lang_items.fn_once_trait()
+            && self.tcx.closure_kind(closure) != ty::ClosureKind::FnOnce {
+            env = boxed_env(&env);
+        }
+
+        let arity = match self.operand_ty(body, &args[1]).sty {
+            ty::TyTuple(tys) => tys.len(),
+            _ => 0,
+        };
+        spread_call(&codegen::Item(closure).to_string(), &env, &codegen::Operand(&args[1]).to_string(), arity)
+    }
+
+    /// Get the type of `lvalue`.
+    ///
+    /// The fields of tuples and closure environments are rewritten to indices by `TupleFields`,
+    /// which the MIR's own type lookup would take for array indexing, so these are looked up here.
+    fn lvalue_ty(&self, body: &repr::Mir<'a>, lvalue: &repr::Lvalue<'a>) -> ty::Ty<'a> {
+        let (base, elem) = match lvalue {
+            &repr::Lvalue::Projection(box repr::Projection { ref base, ref elem }) => (base, elem),
+            _ => return lvalue.ty(body, self.tcx).to_ty(self.tcx),
+        };
+
+        let base_ty = self.lvalue_ty(body, base);
+        let field = match elem {
+            &repr::ProjectionElem::Index(repr::Operand::Constant(repr::Constant {
+                literal: repr::Literal::Value { value: ConstVal::Integral(n) },
+                ..
+            })) => n.to_u64_unchecked() as usize,
+            _ => return LvalueTy::Ty { ty: base_ty }.projection_ty(self.tcx, elem).to_ty(self.tcx),
+        };
+
+        match base_ty.sty {
+            ty::TyTuple(tys) => tys[field],
+            ty::TyClosure(_, ref substs) => substs.upvar_tys[field],
+            _ => LvalueTy::Ty { ty: base_ty }.projection_ty(self.tcx, elem).to_ty(self.tcx),
+        }
+    }
+
+    /// Get the type of `operand`, taking the rewritten tuple fields into account.
+    fn operand_ty(&self, body: &repr::Mir<'a>, operand: &repr::Operand<'a>) -> ty::Ty<'a> {
+        match operand {
+            &repr::Operand::Consume(ref lvalue) => self.lvalue_ty(body, lvalue),
+            &repr::Operand::Constant(ref constant) => constant.ty,
+        }
+    }
+
+    /// Get the JavaScript of the value of `ty` with all bytes zero, if it has one.
+    ///
+    /// Enums and references have no such value, as all-zero bytes may not be valid.
+    fn zero(&self, ty: ty::Ty<'a>) -> Option<String> {
+        match ty.sty {
+            ty::TyBool => Some("false".to_string()),
+            ty::TyChar | ty::TyInt(_) | ty::TyUint(_) | ty::TyFloat(_) => Some("0".to_string()),
+            ty::TyRawPtr(_) => Some("null".to_string()),
+            ty::TyStruct(adt, substs) => adt.struct_variant().fields.iter().enumerate().map(|(n, field)| {
+                self.zero(field.ty(self.tcx, substs)).map(|zero| {
+                    format!(",{}:{}", codegen::Field(repr::Field::new(n)), zero)
+                })
+            }).collect::<Option<Vec<_>>>().map(|fields| format!("{{d:0{}}}", fields.concat())),
+            ty::TyTuple(tys) => tys.iter().map(|&ty| self.zero(ty)).collect::<Option<Vec<_>>>()
+                .map(|elems| format!("[{}]", elems.join(","))),
+            ty::TyArray(elem, len) => self.zero(elem).map(|zero| format!("__cyano_repeat({},{})", zero, len)),
+            _ => None,
+        }
+    }
+
+    /// Get the nominal size and alignment of `ty` in bytes, if it has one.
+    ///
+    /// Values have no memory layout in JS, so this is the layout a native target would give the
+    /// type, if its fields were ordered by alignment. Pointers are 32 bits, like `usize`, and
+    /// pointers to dynamically sized types carry a length as well. Enums and other types, whose
+    /// layout depends on the target, have no size.
+    fn layout(&self, ty: ty::Ty<'a>) -> Option<(u32, u32)> {
+        match ty.sty {
+            ty::TyRawPtr(ty::TypeAndMut { ty, .. }) | ty::TyRef(_, ty::TypeAndMut { ty, .. }) => match ty.sty {
+                ty::TySlice(_) | ty::TyStr | ty::TyTrait(_) => Some((8, 4)),
+                _ => Some((4, 4)),
+            },
+            ty::TyFnPtr(_) => Some((4, 4)),
+            ty::TyFnDef(..) => Some((0, 1)),
+            ty::TyStruct(adt, substs) => adt.struct_variant().fields.iter()
+                .map(|field| self.layout(field.ty(self.tcx, substs))).collect::<Option<Vec<_>>>()
+                .map(|fields| aggregate_layout(&fields)),
+            ty::TyTuple(tys) => tys.iter().map(|&ty| self.layout(ty)).collect::<Option<Vec<_>>>()
+                .map(|fields| aggregate_layout(&fields)),
+            ty::TyArray(elem, len) => self.layout(elem).map(|(size, align)| (size * len as u32, align)),
+            _ => prim_size(ty).map(|size| (size, size)),
+        }
+    }
+
+    /// Is consuming `lvalue` a move rather than a copy?
+    fn moves_by_default(&self, body: &repr::Mir<'a>, lvalue: &repr::Lvalue<'a>) -> bool {
+        let ty = self.lvalue_ty(body, lvalue).subst(self.tcx, self.current().1);
+        ty.moves_by_default(self.tcx, &self.tcx.empty_parameter_environment(), DUMMY_SP)
+    }
+
+    /// Does `ty` have drop glue?
+    fn needs_drop(&self, ty: ty::Ty<'a>) -> bool {
+        self.tcx.type_needs_drop_given_env(ty, &self.tcx.empty_parameter_environment())
+    }
+
+    /// Get the name of the intrinsic `id`, if it is one.
+    fn intrinsic_name(&self, id: DefId) -> Option<String> {
+        match self.tcx.lookup_item_type(id).ty.sty {
+            ty::TyFnDef(_, _, fty) if fty.abi == Abi::RustIntrinsic =>
+                Some(self.tcx.item_name(id).as_str().to_string()),
+            _ => None,
+        }
+    }
+
+    /// Write a call to intrinsic `name`, storing the result in `dest`.
+    fn write_intrinsic(&self, body: &repr::Mir<'a>, span: Span, name: &str, substs: &'a Substs<'a>, dest: &repr::Lvalue<'a>,
+                       args: &[repr::Operand<'a>]) -> fmt::Result {
+        let js_args: Vec<_> = args.iter().map(|x| codegen::Operand(x).to_string()).collect();
+        let mem = mem_intrinsic(name, &js_args);
+        let float = float_intrinsic(name, &js_args);
+
+        match name {
+            // This is folded into a constant, so `Drop`s of types without drop glue can be
+            // skipped entirely in generic code.
+            "needs_drop" => self.write_assign(dest, if self.needs_drop(substs.type_at(0)) { "true" } else { "false" }),
+            _ if mem.is_some() => self.out(|f| write!(f, "{}", mem.unwrap())),
+            // There is no flat memory to fill, so aggregates can only be zeroed, which sets each
+            // of their fields to zero instead.
+            "write_bytes" => {
+                let ty = substs.type_at(0);
+                let zero_byte = match args[1] {
+                    repr::Operand::Constant(repr::Constant {
+                        literal: repr::Literal::Value { value: ConstVal::Integral(val) }, ..
+                    }) => val.to_u64_unchecked() == 0,
+                    _ => false,
+                };
+                let single = match args[2] {
+                    repr::Operand::Constant(repr::Constant {
+                        literal: repr::Literal::Value { value: ConstVal::Integral(count) }, ..
+                    }) => count.to_u64_unchecked() == 1,
+                    _ => false,
+                };
+
+                match (int_bits(ty), zero_byte, self.zero(ty)) {
+                    // Bytes are filled as is.
+                    (Some((_, 8)), _, _) =>
+                        self.out(|f| write!(f, "__cyano_set({},{},{});", codegen::Operand(&args[0]),
+                                            codegen::Operand(&args[1]), codegen::Operand(&args[2]))),
+                    (_, true, Some(ref zero)) if single =>
+                        self.out(|f| write!(f, "{}.set({});", codegen::Operand(&args[0]), zero)),
+                    // The zero value is evaluated for each element, so they don't alias.
+                    (_, true, Some(ref zero)) =>
+                        self.out(|f| write!(f, "(function(d,n){{for(var i=0;i<n;i++)d[i]={}}})({}.get(),{});", zero,
+                                            codegen::Operand(&args[0]), codegen::Operand(&args[2]))),
+                    _ => self.fail(CodegenError::Unsupported {
+                        feature: "write_bytes with non-zero bytes (or into types without a zero value)",
+                        span: span,
+                    }),
+                }
+            },
+            "transmute" => {
+                let from = self.operand_ty(body, &args[0]);
+                let to = self.lvalue_ty(body, dest);
+
+                match to.sty {
+                    // Transmuting a slice reference into its raw representation (as done by
+                    // `len`) must split it into the data pointer and the length, as the JS array
+                    // carries its length itself.
+                    ty::TyStruct(..) if is_slice_ref(from) =>
+                        self.write_assign(dest, &slice_repr(&codegen::Operand(&args[0]).to_string())),
+                    // Integers are transmuted into C-like enums by their discriminant. In checked
+                    // mode, the discriminant must be the one of some variant.
+                    ty::TyEnum(adt, _) if from.is_integral() && adt.variants.iter().all(|v| v.fields.is_empty()) => {
+                        let discrs: Vec<_> = adt.variants.iter()
+                            .map(|variant| codegen::Int(variant.disr_val).to_string())
+                            .collect();
+                        self.write_assign(dest, &int_to_enum(self.config.checked(), &discrs,
+                                                             &codegen::Operand(&args[0]).to_string()))
+                    },
+                    // Every other transmute is assumed to preserve the JS representation.
+                    _ => self.write_assign(dest, &codegen::Operand(&args[0]).to_string()),
+                }
+            },
+            "try" => self.out(|f| write!(f, "{}", try_call(&codegen::Operand(&args[0]).to_string(),
+                                                           &codegen::Operand(&args[1]).to_string(),
+                                                           &codegen::Operand(&args[2]).to_string(), dest))),
+            // Dynamically sized values are measured, and anything else has its nominal size.
+            "size_of_val" => match (&substs.type_at(0).sty, self.layout(substs.type_at(0))) {
+                (&ty::TyStr, _) => self.write_assign(dest, &format!("new TextEncoder().encode({}).length",
+                                                                    codegen::Operand(&args[0]))),
+                (&ty::TySlice(elem), _) => match self.layout(elem) {
+                    Some((size, _)) =>
+                        self.write_assign(dest, &format!("{}.get().length*{}", codegen::Operand(&args[0]), size)),
+                    None => self.fail(CodegenError::Unsupported {
+                        feature: "size_of_val of slices of enums and other types without a nominal layout",
+                        span: span,
+                    }),
+                },
+                (_, Some((size, _))) => self.write_assign(dest, &size.to_string()),
+                (_, None) => self.fail(CodegenError::Unsupported {
+                    feature: "size_of_val of enums and other types without a nominal layout",
+                    span: span,
+                }),
+            },
+            // Sizes of types are queried through this intrinsic, as this MIR has no dedicated
+            // rvalue for them. Aggregates get their nominal size, so zero-sized types are still
+            // zero-sized.
+            "size_of" => match self.layout(substs.type_at(0)) {
+                Some((size, _)) => self.write_assign(dest, &size.to_string()),
+                None => self.fail(CodegenError::Unsupported {
+                    feature: "size_of of enums and other types without a nominal layout",
+                    span: span,
+                }),
+            },
+            // Types are aligned like their nominal layout, and dynamically sized values like
+            // their elements (bytes, in the case of `str`).
+            "min_align_of" | "min_align_of_val" => {
+                let align = match substs.type_at(0).sty {
+                    ty::TyStr if name == "min_align_of_val" => Some(1),
+                    ty::TySlice(elem) if name == "min_align_of_val" => self.layout(elem).map(|(_, align)| align),
+                    _ => self.layout(substs.type_at(0)).map(|(_, align)| align),
+                };
+
+                match align {
+                    Some(align) => self.write_assign(dest, &align.to_string()),
+                    None => self.fail(CodegenError::Unsupported {
+                        feature: "alignment of enums and other types without a nominal layout",
+                        span: span,
+                    }),
+                }
+            },
+            // This is merely a hook for testing the dataflow analyses of rustc.
+            "rustc_peek" => self.write_assign(dest, &codegen::Operand(&args[0]).to_string()),
+            // The divisor is assumed to be nonzero, so no checks are emitted. Integer division
+            // must round towards zero, whereas the JS remainder already matches Rust's.
+            "unchecked_div" => self.write_assign(dest, &format!("Math.trunc(({})/({}))", codegen::Operand(&args[0]),
+                                                                codegen::Operand(&args[1]))),
+            "unchecked_rem" => self.write_assign(dest, &format!("({})%({})", codegen::Operand(&args[0]),
+                                                                codegen::Operand(&args[1]))),
+            "fmaf32" | "fmaf64" => self.write_assign(dest, &mul_add(self.config.precise_fma, name == "fmaf32",
+                                                                    &codegen::Operand(&args[0]).to_string(),
+                                                                    &codegen::Operand(&args[1]).to_string(),
+                                                                    &codegen::Operand(&args[2]).to_string())),
+            // `Math.min` and `Math.max` return NaN if either operand is NaN, whereas Rust returns
+            // the other operand.
+            "minnumf32" | "minnumf64" =>
+                self.write_assign(dest, &format!("{0}!=={0}?{1}:{1}!=={1}?{0}:Math.min({0},{1})",
+                                                 codegen::Operand(&args[0]), codegen::Operand(&args[1]))),
+            "maxnumf32" | "maxnumf64" =>
+                self.write_assign(dest, &format!("{0}!=={0}?{1}:{1}!=={1}?{0}:Math.max({0},{1})",
+                                                 codegen::Operand(&args[0]), codegen::Operand(&args[1]))),
+            "bswap" => self.write_assign(dest, &bswap(substs.type_at(0), &codegen::Operand(&args[0]).to_string())),
+            _ if float.is_some() => self.write_assign(dest, &float.unwrap()),
+            // JavaScript is single-threaded, so atomic operations are plain accesses of the
+            // pointee, and the memory orderings (the suffix of the name) are ignored.
+            _ if name.starts_with("atomic_") => match name.split('_').nth(1).unwrap() {
+                // Execution is single-threaded, so there is nothing for fences to order.
+                "fence" | "singlethreadfence" => Ok(()),
+                "load" => self.write_assign(dest, &format!("{}.get()", codegen::Operand(&args[0]))),
+                "store" => self.out(|f| write!(f, "{}.set({});", codegen::Operand(&args[0]), codegen::Operand(&args[1]))),
+                // The read-modify-write operations return the old value.
+                "xchg" => {
+                    self.write_assign(dest, &format!("{}.get()", codegen::Operand(&args[0])))?;
+                    self.out(|f| write!(f, "{}.set({});", codegen::Operand(&args[0]), codegen::Operand(&args[1])))
+                },
+                // The compare-and-exchange operations return the old value along with whether it
+                // was exchanged. As nothing else runs in between, the weak ones never fail
+                // spuriously.
+                "cxchg" | "cxchgweak" => self.write_assign(dest, &atomic_cxchg(&codegen::Operand(&args[0]).to_string(),
+                                                                               &codegen::Operand(&args[1]).to_string(),
+                                                                               &codegen::Operand(&args[2]).to_string())),
+                op => match atomic_rmw(op, &codegen::LvalueGet(dest).to_string(), &codegen::Operand(&args[1]).to_string()) {
+                    Some(new) => {
+                        self.write_assign(dest, &format!("{}.get()", codegen::Operand(&args[0])))?;
+                        self.out(|f| write!(f, "{}.set({});", codegen::Operand(&args[0]), coerce(substs.type_at(0), &new)))
+                    },
+                    None => self.fail(CodegenError::Unsupported {
+                        feature: "atomic intrinsics other than fences, loads, stores and read-modify-writes",
+                        span: span,
+                    }),
+                },
+            },
+            _ => self.fail(CodegenError::Unsupported {
+                feature: "calls to this intrinsic",
+                span: span,
+            }),
+        }
+    }
+
+    fn write_statement(&self, body: &repr::Mir<'a>, statement: &repr::Statement<'a>) -> fmt::Result {
+        if let repr::StatementKind::Assign(_, ref rvalue) = statement.kind {
+            self.diagnose_rvalue(body, statement.source_info.span, rvalue);
+
+            // There is no way to run inline assembly.
+            if let &repr::Rvalue::InlineAsm { .. } = rvalue {
+                return self.fail(CodegenError::UnsupportedRvalue {
+                    kind: error::rvalue_name(rvalue),
+                    span: statement.source_info.span,
+                });
+            }
+        }
+
+        match statement.kind {
+            repr::StatementKind::Assign(ref lvalue, repr::Rvalue::BinaryOp(binop, ref x, ref y))
+                if (binop == repr::BinOp::Div || binop == repr::BinOp::Rem)
+                    && self.operand_ty(body, x).is_integral() => {
+                let (x_js, y_js) = (codegen::Operand(x).to_string(), codegen::Operand(y).to_string());
+                let mut res = int_div(binop, &x_js, &y_js);
+                if self.config.asmjs {
+                    res = coerce(self.operand_ty(body, x), &res);
+                }
+
+                if let Some(n) = codegen::fold(binop, x, y) {
+                    self.write_assign(lvalue, &codegen::Int(n).to_string())
+                } else if self.config.checked() {
+                    self.write_assign(lvalue, &check_divisor(binop, &y_js, &res))
+                } else {
+                    self.write_assign(lvalue, &res)
+                }
+            },
+            // Arithmetic is coerced to its type, as asm.js requires. Comparisons give bools, so
+            // these are left as is.
+            repr::StatementKind::Assign(ref lvalue, ref rvalue @ repr::Rvalue::BinaryOp(..))
+            | repr::StatementKind::Assign(ref lvalue, ref rvalue @ repr::Rvalue::UnaryOp(repr::UnOp::Neg, _))
+                if self.config.asmjs && match rvalue {
+                    &repr::Rvalue::BinaryOp(binop, ..) => binop != repr::BinOp::Eq && binop != repr::BinOp::Ne
+                        && binop != repr::BinOp::Lt && binop != repr::BinOp::Le
+                        && binop != repr::BinOp::Gt && binop != repr::BinOp::Ge,
+                    _ => true,
+                } => {
+                let x = match rvalue {
+                    &repr::Rvalue::BinaryOp(_, ref x, _) | &repr::Rvalue::UnaryOp(_, ref x) => x,
+                    _ => unreachable!(),
+                };
+                let ty = self.operand_ty(body, x);
+                let res = match rvalue {
+                    // The exact product of 32-bit integers may be beyond the exact range of
+                    // numbers, so its low bits would be lost.
+                    &repr::Rvalue::BinaryOp(repr::BinOp::Mul, ref x, ref y) if int_bits(ty).map_or(false, |(_, bits)| bits == 32) =>
+                        imul(&codegen::Operand(x).to_string(), &codegen::Operand(y).to_string()),
+                    _ => codegen::Rvalue(rvalue).to_string(),
+                };
+                self.write_assign(lvalue, &coerce(ty, &res))
+            },
+            repr::StatementKind::Assign(ref lvalue, ref rvalue @ repr::Rvalue::CheckedBinaryOp(..))
+                if match rvalue {
+                    &repr::Rvalue::CheckedBinaryOp(binop, ref x, ref y) => codegen::fold(binop, x, y).is_some(),
+                    _ => false,
+                } =>
+                // Constants, which don't overflow, are folded by the fallback.
+                self.write_assign(lvalue, &codegen::Rvalue(rvalue).to_string()),
+            repr::StatementKind::Assign(ref lvalue, repr::Rvalue::CheckedBinaryOp(binop, ref x, ref y)) => {
+                let res = codegen::Rvalue(&repr::Rvalue::BinaryOp(binop, x.clone(), y.clone())).to_string();
+                let (signed, bits) = int_bits(self.operand_ty(body, x)).unwrap_or((true, 64));
+                self.write_assign(lvalue, &checked_binop(binop, signed, bits, &res, &codegen::Operand(x).to_string(),
+                                                         &codegen::Operand(y).to_string()))
+            },
+            repr::StatementKind::Assign(ref lvalue, repr::Rvalue::Cast(repr::CastKind::Unsize, ref x, target))
+                if target.builtin_deref(true, ty::NoPreference).map_or(false, |x| x.ty.is_trait()) => {
+                let source = self.operand_ty(body, x).builtin_deref(true, ty::NoPreference).unwrap().ty;
+                let target = target.builtin_deref(true, ty::NoPreference).unwrap().ty;
+                let vtable = self.vtable(statement.source_info.span, source, target)?;
+                self.write_assign(lvalue, &format!("{{data:{},vtable:{}}}", codegen::Operand(x), vtable))
+            },
+            repr::StatementKind::Assign(ref lvalue, repr::Rvalue::Cast(repr::CastKind::Misc, ref x, target))
+                if target.sty == ty::TyChar && self.config.checked() => {
+                // Chars are represented by their code point, so the cast is the identity, but the
+                // value must still be a valid scalar value.
+                self.write_assign(lvalue, &format!("function(c){{return c>0x10FFFF||(c>=0xD800&&c<=0xDFFF)\
+                                                    ?__cyano_trap(\"invalid char cast\"):c}}({})",
+                                                   codegen::Operand(x)))
+            },
+            repr::StatementKind::Assign(ref lvalue, repr::Rvalue::Cast(repr::CastKind::Misc, ref x, target))
+                if target.is_integral() && self.operand_ty(body, x).builtin_deref(true, ty::NoPreference).is_some() => {
+                // Pointers are objects, so they have no address. Instead, each pointee is given a
+                // stable id, which is compared and hashed like an address would be.
+                self.write_assign(lvalue, &format!("__cyano_addr({})", codegen::Operand(x)))
+            },
+            _ => self.out(|f| write!(f, "{};", codegen::Statement(statement))),
+        }
+    }
+
+    /// Get the vtable of the type `source` coerced into trait object `target` at `span`.
+    ///
+    /// Trait objects are represented by an object holding the pointer to the data and the vtable,
+    /// which maps the trait methods' names to their implementations for `source`. The methods of
+    /// the supertraits are included, as they can be called on the trait object as well.
+    fn vtable(&self, span: Span, source: ty::Ty<'a>, target: ty::Ty<'a>) -> Result<String, fmt::Error> {
+        let principal = match target.sty {
+            ty::TyTrait(ref obj) => obj.principal.with_self_ty(self.tcx, source),
+            // Only trait objects have vtables.
+            _ => unreachable!(),
+        };
+
+        let mut entries = Vec::new();
+        for trait_ref in traits::supertraits(self.tcx, principal) {
+            for item in self.tcx.trait_items(trait_ref.def_id()).iter() {
+                if let ty::MethodTraitItem(ref method) = *item {
+                    let entry = if let ty::TyClosure(closure, _) = source.sty {
+                        self.schedule(closure, Substs::empty(self.tcx));
+                        closure_entry(&codegen::Item(closure).to_string())
+                    } else {
+                        match resolve::impl_method(self.tcx, method.def_id, trait_ref.skip_binder().substs) {
+                            Some((id, substs)) => {
+                                self.schedule(id, substs);
+                                codegen::Instance(id, substs).to_string()
+                            },
+                            None => return self.fail(CodegenError::Unsupported {
+                                feature: "trait objects of types with unresolvable methods",
+                                span: span,
+                            }),
+                        }
+                    };
+
+                    entries.push((codegen::Item(method.def_id).to_string(), entry));
+                }
+            }
+        }
+
+        Ok(vtable_object(&entries))
+    }
+
+    /// Check if dropping `location` runs any drop glue.
+    fn has_drop_glue(&self, body: &repr::Mir<'a>, location: &repr::Lvalue<'a>) -> bool {
+        let moved = self.moved.replace(Vec::new());
+        let is_moved = is_moved(&moved, location);
+        self.moved.replace(moved);
+
+        // Types without drop glue (e.g. integers) have nothing to clean up. Locals, which are
+        // moved out of, are dropped by their new owner, and there are no drop flags telling if
+        // the move happened, so these are left alone to avoid double drops.
+        !is_moved && self.needs_drop(self.lvalue_ty(body, location))
+    }
+
+    /// Write the drop glue of `location`.
+    fn write_drop(&self, body: &repr::Mir<'a>, location: &repr::Lvalue<'a>) -> fmt::Result {
+        if self.has_drop_glue(body, location) {
+            self.out(|f| write!(f, "delete {};", codegen::LvalueGet(location)))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Write a comment with the source line of `span`, ending the line.
+    fn write_line_comment(&self, span: Span) -> fmt::Result {
+        if span == DUMMY_SP || self.tcx.sess.opts.debuginfo == DebugInfoLevel::NoDebugInfo {
+            return Ok(());
+        }
+
+        let loc = self.tcx.sess.codemap().lookup_char_pos(span.lo);
+        self.out(|f| write!(f, "//{}:{}\n", loc.file.name, loc.line))
+    }
+
+    /// Write the branch to `otherwise`, taken when no case of a switch matches.
+    ///
+    /// If MIR marks the branch unreachable, the match was exhaustive, and the value is corrupt, so
+    /// it traps right away rather than going through the block.
+    fn write_otherwise(&self, body: &repr::Mir<'a>, otherwise: repr::BasicBlock) -> fmt::Result {
+        if is_unreachable(&body.basic_blocks()[otherwise]) {
+            self.out(|f| write!(f, "{}", NON_EXHAUSTIVE))
+        } else {
+            self.goto(otherwise)
+        }
     }
 
-    fn write_bb(&self, bb: repr::BasicBlockData) -> fmt::Result {
+    fn write_bb(&self, body: &repr::Mir<'a>, bb: repr::BasicBlockData<'a>) -> fmt::Result {
         use rustc::mir::repr::TerminatorKind;
 
         for i in bb.statements {
-            self.out(|f| write!(f, "{}", codegen::Statement(&i)))?;
+            self.write_statement(body, &i)?;
+
+            if self.config.line_comments {
+                self.write_line_comment(i.source_info.span)?;
+            }
         }
 
-        match bb.terminator.unwrap().kind {
+        let terminator = bb.terminator.unwrap();
+        let span = terminator.source_info.span;
+        match terminator.kind {
+            // Returning from anywhere (e.g. nested loops) ends up jumping to the return block, so
+            // the function returns right away instead of going through the goto loop once more.
+            TerminatorKind::Goto { target } if is_return(&body.basic_blocks()[target]) => self.out(|f| write!(f, "return r;")),
             TerminatorKind::Goto { target } => self.goto(target),
             TerminatorKind::If { cond, targets: (branch_true, branch_false) } => {
                 self.out(|f| write!(f, "if({}){{", codegen::Operand(&cond)))?;
@@ -113,6 +1329,10 @@ impl<'a> Compiler<'a> {
                 // End the if statement.
                 self.out(|f| write!(f, "}}"))
             },
+            // Values of empty enums can't exist, so reaching the match at all is an error. The
+            // discriminant isn't read, as there is no value to read it from.
+            TerminatorKind::Switch { adt_def: def, .. } if def.variants.is_empty() =>
+                self.out(|f| write!(f, "__cyano_trap(\"Matched on a value of an empty enum.\");")),
             TerminatorKind::Switch { discr: disc, adt_def: def, targets } => {
                 // Begin the switch statement.
                 self.out(|f| write!(f, "switch({}){{", codegen::Discriminant(&disc)))?;
@@ -125,36 +1345,80 @@ impl<'a> Compiler<'a> {
                     self.goto(bb)?;
                 }
 
+                // Every variant has a case, so anything else is a corrupt discriminant.
+                self.out(|f| write!(f, "default:{}", INVALID_DISCRIMINANT))?;
+
                 // End the statement.
                 self.out(|f| write!(f, "}}"))
             },
             TerminatorKind::SwitchInt { discr: disc, values, targets, .. } => {
+                // The last target is taken when no value matches.
+                let otherwise = targets.get(values.len()).cloned();
+
+                // Dense matches are lowered to a jump table, indexed by the value.
+                let cases: Option<Vec<_>> = values.iter().zip(&targets).map(|(case, bb)| match case {
+                    &ConstVal::Integral(int) => Some((int.to_u64_unchecked() as i64, bb.index())),
+                    &ConstVal::Char(c) => Some((c as i64, bb.index())),
+                    _ => None,
+                }).collect();
+                if let (Some(cases), Some(otherwise)) = (cases, otherwise) {
+                    if let Some((offset, table)) = jump::jump_table(&cases, otherwise.index()) {
+                        let table: Vec<_> = table.iter().map(|bb| bb.to_string()).collect();
+                        self.out(|f| write!(f, "g=[{}][{}-({})];", table.join(","), codegen::LvalueGet(&disc), offset))?;
+                        // Values outside the table give `undefined`.
+                        self.out(|f| write!(f, "if(g===undefined){{"))?;
+                        self.write_otherwise(body, otherwise)?;
+                        return self.out(|f| write!(f, "}}continue t;"));
+                    }
+                }
+
                 // Begin the switch statement.
                 self.out(|f| write!(f, "switch({}){{", codegen::LvalueGet(&disc)))?;
 
                 // Fill in the cases.
-                for (case, bb) in values.iter().zip(targets) {
+                for (case, bb) in values.iter().zip(&targets) {
                     self.out(|f| write!(f, "case {}:", codegen::Literal(&repr::Literal::Value {
                         // FIXME: I'm almost certain that there is a way to eliminate this clone,
                         // but it is messy, so it gets to stay for now.
                         value: case.clone(),
                     })))?;
-                    self.goto(bb)?;
+                    self.goto(*bb)?;
+                }
+
+                if let Some(otherwise) = otherwise {
+                    self.out(|f| write!(f, "default:"))?;
+                    self.write_otherwise(body, otherwise)?;
                 }
 
                 // End the statement.
                 self.out(|f| write!(f, "}}"))
             },
+            TerminatorKind::Assert { target, .. } if self.config.release => self.goto(target),
+            TerminatorKind::Assert { cond, expected, msg, target, .. } => {
+                let msg = match msg {
+                    // Pass on the actual length and index, so they are part of the message.
+                    repr::AssertMessage::BoundsCheck { len, index } =>
+                        bounds_message(&codegen::Operand(&len).to_string(), &codegen::Operand(&index).to_string()),
+                    // The condition is the overflow flag of the checked operation, and the message
+                    // names the operation.
+                    repr::AssertMessage::Math(err) => format!("{:?}", error::math_message(err)),
+                };
+                let js = assert(&codegen::Operand(&cond).to_string(), expected, &msg);
+                self.out(|f| write!(f, "{}", js))?;
+
+                self.goto(target)
+            },
             TerminatorKind::Resume => Ok(()),
             TerminatorKind::Return => self.out(|f| write!(f, "return r;")),
             TerminatorKind::Unreachable =>
-                self.out(|f| write!(f, "alert('Cyano error: Basic block terminated with unreachable.');")),
+                self.out(|f| write!(f, "{}", UNREACHABLE)),
             TerminatorKind::Drop { location, target, .. } => {
-                self.out(|f| write!(f, "delete {};", codegen::LvalueGet(&location)))?;
+                self.write_drop(body, &location)?;
                 self.goto(target)
             },
             TerminatorKind::DropAndReplace { location, value, target, .. } => {
-                self.out(|f| write!(f, "{};", codegen::LvalueSet(&location, codegen::Expr::Rvalue(&repr::Rvalue::Use(value)))))?;
+                let js = drop_and_replace(&location, &value, self.has_drop_glue(body, &location));
+                self.out(|f| write!(f, "{}", js))?;
                 self.goto(target)
             },
             TerminatorKind::Call {
@@ -163,36 +1427,1454 @@ impl<'a> Compiler<'a> {
                 destination,
                 ..
             } => {
-                if let repr::Operand::Constant(repr::Constant {
-                    literal: repr::Literal::Item { def_id: _, .. },
+                if let &repr::Operand::Constant(repr::Constant {
+                    literal: repr::Literal::Item { def_id, substs },
                     ..
-                }) = func {
-                    // FIXME:
-                    // Make sure it is compiled afterwaards.
-                    // self.delayed_fns.push(def_id);
+                }) = &func {
+                    // Generic functions can call other generic functions, so the arguments must
+                    // be substituted to get the types of this instance.
+                    let substs = self.subst(substs);
+
+                    if substs.self_ty().map_or(false, |ty| ty.is_trait()) {
+                        // Dynamic dispatch: look the method up in the trait object's vtable, and
+                        // pass the data pointer as the receiver.
+                        let args: Vec<_> = args.iter().map(|x| codegen::Operand(x).to_string()).collect();
+                        let call = virtual_call(&codegen::Item(def_id).to_string(), &args);
+
+                        return if let Some((return_value, bb)) = destination {
+                            self.write_assign(&return_value, &call)?;
+
+                            // Continue to the next BB.
+                            self.goto(bb)
+                        } else {
+                            // The method is diverging.
+                            self.out(|f| write!(f, "{};", call))
+                        };
+                    }
+
+                    if let Some(name) = self.intrinsic_name(def_id) {
+                        let (return_value, bb) = match (diverging_intrinsic(&name), destination) {
+                            (Some(js), _) => return self.out(|f| write!(f, "{}", js)),
+                            (None, Some(destination)) => destination,
+                            (None, None) => return self.fail(CodegenError::Unsupported {
+                                feature: "diverging intrinsics other than abort and unreachable",
+                                span: span,
+                            }),
+                        };
+                        if (name == "fmaf32" || name == "fmaf64") && !self.config.precise_fma {
+                            self.diagnose(span, "the multiply-add isn't fused, so it rounds twice");
+                        }
+                        self.write_intrinsic(body, span, &name, substs, &return_value, &args)?;
+
+                        // Continue to the next BB.
+                        return self.goto(bb);
+                    }
+
+                    if let (true, &Some((ref return_value, bb))) =
+                        (self.tcx.item_path_str(def_id) == "core::clone::Clone::clone", &destination) {
+                        self.write_clone(def_id, substs, return_value, &args)?;
 
-                    if let Some((return_value, bb)) = destination {
-                        self.out(|f| write!(f, "{}", codegen::Expr::Call(&return_value, &args)))?;
+                        // Continue to the next BB.
+                        return self.goto(bb);
+                    }
+
+                    if let Some((ref return_value, bb)) = destination {
+                        if self.write_override(def_id, substs, return_value, &args)? {
+                            // Continue to the next BB.
+                            return self.goto(bb);
+                        }
+                    }
+
+                    if let (Some(lvalue), &Some((ref return_value, bb))) = (self.inline_call(def_id, &args),
+                                                                           &destination) {
+                        // Read the accessed lvalue directly instead of calling the accessor.
+                        self.out(|f| write!(f, "{};", codegen::LvalueSet(return_value, codegen::Expr::Rvalue(
+                            &repr::Rvalue::Use(repr::Operand::Consume(lvalue))))))?;
+
+                        // Continue to the next BB.
+                        return self.goto(bb);
+                    }
+
+                    // Only the `Fn*` trait methods call the closure. The first type argument of
+                    // other items (e.g. `Wrapper::<F>::new`) can be a closure just as well.
+                    let lang_items = &self.tcx.lang_items;
+                    let fn_trait = is_fn_trait(self.tcx.trait_of_item(def_id), &[lang_items.fn_trait(),
+                                                                                lang_items.fn_mut_trait(),
+                                                                                lang_items.fn_once_trait()]);
+                    if let (true, Some(&ty::TyClosure(closure, _))) = (fn_trait, substs.self_ty().map(|ty| &ty.sty)) {
+                        self.schedule(closure, Substs::empty(self.tcx));
+                        let call = self.closure_call(body, def_id, closure, &args);
+
+                        return if let Some((return_value, bb)) = destination {
+                            self.write_assign(&return_value, &call)?;
+
+                            // Continue to the next BB.
+                            self.goto(bb)
+                        } else {
+                            // The closure is diverging.
+                            self.out(|f| write!(f, "{};", call))
+                        };
+                    }
+
+                    let (callee, substs) = self.callee(def_id, substs);
+
+                    if let Some((repr::Lvalue::ReturnPointer, bb)) = destination {
+                        // References read the locals they borrow lazily, so a jump, which
+                        // reassigns the locals, would change the referents of references passed
+                        // on to the next iteration. Such functions are called normally.
+                        let mut borrows = LocalBorrows::default();
+                        borrows.visit_mir(body);
+
+                        if (callee, substs) == self.current() && is_return(&body.basic_blocks()[bb]) && !borrows.found {
+                            // A self-call in tail position is a jump to the start with the new
+                            // arguments, which avoids growing the stack.
+                            let js_args = codegen::Args(&args).to_string();
+                            self.out(|f| write!(f, "{}", tail_jump(&js_args, args.len())))?;
+                            return self.goto(repr::START_BLOCK);
+                        }
+                    }
+
+                    // Call the instance directly.
+                    self.schedule(callee, substs);
+                    let call = format!("{}({})", self.name(callee, substs), codegen::Args(&args));
+
+                    return if let Some((return_value, bb)) = destination {
+                        self.write_assign(&return_value, &call)?;
 
                         // Continue to the next BB.
                         self.goto(bb)
                     } else {
                         // The function is diverging.
-                        self.out(|f| write!(f, "{}(", codegen::Operand(&func)))?;
+                        self.out(|f| write!(f, "{};", call))
+                    };
+                }
 
-                        // List the argument.
-                        for i in args {
-                            self.out(|f| write!(f, "{},", codegen::Operand(&i)))?;
-                        }
+                // Anything else is a function pointer.
+                if let Some((return_value, bb)) = destination {
+                    self.out(|f| write!(f, "{};", codegen::LvalueSet(&return_value,
+                                                                     codegen::Expr::Call(&func, &args))))?;
 
-                        // Close the argument list.
-                        self.out(|f| write!(f, ")"))
-                    }
+                    // Continue to the next BB.
+                    self.goto(bb)
                 } else {
-                    unimplemented!();
+                    // The function is diverging.
+                    self.out(|f| write!(f, "{};", codegen::Expr::Call(&func, &args)))
                 }
             }
-            _ => unimplemented!(),
+            ref kind => self.fail(CodegenError::UnsupportedTerminator {
+                kind: error::terminator_name(kind),
+                span: span,
+            }),
         }
     }
 }
+
+/// Get the JavaScript trapping with the message `msg` (a JavaScript string), unless `cond` is
+/// `expected`.
+fn assert(cond: &str, expected: bool, msg: &str) -> String {
+    format!("if({}({})){{__cyano_trap({});}}", if expected { "!" } else { "" }, cond, msg)
+}
+
+/// Get the JavaScript message of a failed bounds check of the index `index` into `len` elements.
+fn bounds_message(len: &str, index: &str) -> String {
+    format!("\"index out of bounds: len \"+{}+\", index \"+{}", len, index)
+}
+
+/// Get the JavaScript assigning `value` to `location`, after dropping the old value, if `drop`.
+fn drop_and_replace<'a>(location: &'a repr::Lvalue<'a>, value: &'a repr::Operand<'a>, drop: bool) -> String {
+    // The old value is dropped before it is overwritten.
+    let drop = if drop { format!("delete {};", codegen::LvalueGet(location)) } else { String::new() };
+    let value = codegen::Operand(value).to_string();
+    format!("{}{};", drop, codegen::LvalueSet(location, codegen::Expr::Raw(&value)))
+}
+
+/// Get the JavaScript of the function `name`, which constructs the variant with the discriminant
+/// `disr` out of its `fields` fields.
+fn ctor(name: &str, disr: &str, fields: usize) -> String {
+    // Declare the arguments, one for each field.
+    let args: Vec<_> = (0..fields).map(|i| codegen::Arg(repr::Arg::new(i)).to_string()).collect();
+    let mut ctor = format!("function {}({}){{return{{d:{}", name, args.join(","), disr);
+
+    // Write in all the fields.
+    for (i, arg) in args.iter().enumerate() {
+        ctor.push_str(&format!(",{}:{}", codegen::Field(repr::Field::new(i)), arg));
+    }
+
+    // End the object and the function.
+    ctor.push_str("}}");
+
+    ctor
+}
+
+/// Get the JavaScript of the C-like enum with the discriminant `int`, which is checked to be one of
+/// the discriminants `discrs` of its variants, if `checked`.
+fn int_to_enum(checked: bool, discrs: &[String], int: &str) -> String {
+    if checked {
+        format!("function(d){{return[{}].indexOf(d)<0?__cyano_trap(\"invalid enum discriminant: \"+d):{{d:d}}}}({})",
+                discrs.join(","), int)
+    } else {
+        format!("{{d:{}}}", int)
+    }
+}
+
+/// Is the trait `trait_id` (of some item, if it is in one) one of the `Fn*` traits `fn_traits`?
+///
+/// The lang items of these traits are missing without libcore, in which case nothing calls a
+/// closure through them.
+fn is_fn_trait(trait_id: Option<DefId>, fn_traits: &[Option<DefId>]) -> bool {
+    trait_id.is_some() && fn_traits.contains(&trait_id)
+}
+
+/// Get the JavaScript of a reference to the environment `env`, for calling a closure, which takes
+/// its environment by reference, through `call_once`.
+fn boxed_env(env: &str) -> String {
+    format!("function(b){{b.set({});return b}}(__cyano_box())", env)
+}
+
+/// Get the JavaScript of a function of one argument calling the `FnMut` closure `closure` with a
+/// reference to its environment `env`.
+fn fn_mut_closure(closure: &str, env: &str) -> String {
+    format!("function(e){{return function(x){{return {}(e,x)}}}}({})", closure, boxed_env(env))
+}
+
+/// Get the JavaScript of the vtable entry of the `Fn*` trait methods of the closure `closure`.
+///
+/// Closures take their arguments separately rather than as a tuple.
+fn closure_entry(closure: &str) -> String {
+    format!("function(e,t){{return {}.apply(null,[e].concat(t))}}", closure)
+}
+
+/// Get the JavaScript calling the closure `closure` with the environment `env`, and the `arity`
+/// elements of the tuple `args` as the arguments.
+///
+/// Closures are functions taking their environment (an array of the captured variables) first.
+fn spread_call(closure: &str, env: &str, args: &str, arity: usize) -> String {
+    let mut call = format!("{}({}", closure, env);
+    for i in 0..arity {
+        call.push_str(&format!(",{}[{}]", args, i));
+    }
+    call.push(')');
+
+    call
+}
+
+/// Get the JavaScript computing `x*y+z`, in single precision if `single`.
+///
+/// JavaScript has no fused multiply-add, so by default the product is rounded before the
+/// addition, which can make the result differ from Rust's in the last bit. If `precise`, the
+/// result is correctly rounded instead.
+fn mul_add(precise: bool, single: bool, x: &str, y: &str, z: &str) -> String {
+    match (precise, single) {
+        // The product of two `f32`s is exact in a double, so only the sum rounds. Rounding it to
+        // odd keeps it from double rounding, when it is narrowed to `f32`.
+        (true, true) => format!("Math.fround(__cyano_add_odd(({})*({}),{}))", x, y, z),
+        (true, false) => format!("__cyano_fma({},{},{})", x, y, z),
+        (false, _) => format!("({})*({})+({})", x, y, z),
+    }
+}
+
+/// Get the JavaScript of a vtable with the methods `entries`, given by name and implementation.
+fn vtable_object(entries: &[(String, String)]) -> String {
+    let entries: Vec<_> = entries.iter().map(|&(ref name, ref entry)| format!("{}:{}", name, entry)).collect();
+    format!("{{{}}}", entries.join(","))
+}
+
+/// Get the JavaScript calling the method `method` of the trait object `args[0]` with the other
+/// arguments `args[1..]`.
+///
+/// The method is looked up in the vtable, and gets the data pointer as the receiver.
+fn virtual_call(method: &str, args: &[String]) -> String {
+    let mut call = format!("{0}.vtable.{1}({0}.data", args[0], method);
+    for i in &args[1..] {
+        call.push_str(&format!(",{}", i));
+    }
+    call.push(')');
+
+    call
+}
+
+/// Get the JavaScript of a call to the memory intrinsic `name` (copying or filling memory) with
+/// the arguments `args`, if it is one.
+fn mem_intrinsic(name: &str, args: &[String]) -> Option<String> {
+    match name {
+        // Pointers alias the array they point into, so both kinds of copies boil down to an
+        // element-wise copy, which allows the source and destination to overlap.
+        "copy_nonoverlapping" | "copy" => Some(format!("__cyano_copy({},{},{});", args[0], args[1], args[2])),
+        // The volatile versions take the destination first. Going through the runtime call
+        // already ensures that they're not optimized away.
+        "volatile_copy_nonoverlapping_memory" | "volatile_copy_memory" =>
+            Some(format!("__cyano_copy({},{},{});", args[1], args[0], args[2])),
+        "volatile_set_memory" => Some(format!("__cyano_set({},{},{});", args[0], args[1], args[2])),
+        _ => None,
+    }
+}
+
+/// Get the JavaScript calling `func` with `data`, and assigning whether it panicked to `dest`.
+///
+/// The exception is stored as the payload, in `payload`. Aborts aren't caught.
+fn try_call<'a>(func: &str, data: &str, payload: &str, dest: &'a repr::Lvalue<'a>) -> String {
+    format!("try{{{}({});{};}}catch(e){{if(e===__cyano_abort)throw e;{}.set(e);{};}}", func, data,
+            codegen::LvalueSet(dest, codegen::Expr::Raw("0")), payload,
+            codegen::LvalueSet(dest, codegen::Expr::Raw("1")))
+}
+
+/// Get the JavaScript of the float intrinsic `name` of the arguments `args`, if it is one of the
+/// ones implemented by the runtime.
+fn float_intrinsic(name: &str, args: &[String]) -> Option<String> {
+    match name {
+        "copysignf32" | "copysignf64" => Some(format!("__cyano_copysign({},{})", args[0], args[1])),
+        // The rounding mode is assumed to be the default, rounding ties to even.
+        "nearbyintf32" | "nearbyintf64" | "rintf32" | "rintf64" => Some(format!("__cyano_rint({})", args[0])),
+        _ => None,
+    }
+}
+
+/// Get the JavaScript of the sign of the float `x`, which is NaN for NaN, and otherwise keeps the
+/// sign of zeros.
+fn signum(x: &str) -> String {
+    format!("{0}!=={0}?NaN:__cyano_copysign(1,{0})", x)
+}
+
+/// Get the JavaScript of a call to the diverging intrinsic `name`, if it is lowered.
+fn diverging_intrinsic(name: &str) -> Option<&'static str> {
+    match name {
+        "abort" => Some("__cyano_abort();"),
+        // Reaching this is undefined behavior, so it traps like a failed check.
+        "unreachable" => Some("__cyano_trap(\"entered unreachable code\");"),
+        _ => None,
+    }
+}
+
+/// Get the JavaScript declaring `__cyano_ns`, the object exports are assigned to.
+///
+/// This is the global object of `target`, or the object `namespace` on it, if one is set.
+fn namespace(target: Target, namespace: Option<&str>) -> String {
+    let global = match target {
+        Target::Browser => "window",
+        Target::Node => "globalThis",
+        Target::Worker => "self",
+    };
+    match namespace {
+        Some(ns) => format!("var __cyano_ns={0}.{1}={0}.{1}||{{}};", global, ns),
+        None => format!("var __cyano_ns={};", global),
+    }
+}
+
+/// Is `ty` a reference to a slice or a string slice?
+fn is_slice_ref(ty: ty::Ty) -> bool {
+    match ty.sty {
+        ty::TyRef(_, ty::TypeAndMut { ty, .. }) => match ty.sty {
+            ty::TySlice(_) | ty::TyStr => true,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Get the JavaScript compare-and-exchange of the pointer `ptr`, giving the tuple of the old value
+/// and whether it was equal to `old` (and thus replaced by `new`).
+fn atomic_cxchg(ptr: &str, old: &str, new: &str) -> String {
+    format!("function(p,o,n){{var v=p.get();if(v===o)p.set(n);return[v,v===o]}}({},{},{})", ptr, old, new)
+}
+
+/// Get the JavaScript expression of the value written by the atomic read-modify-write operation
+/// `op` (e.g. `xadd` of `atomic_xadd_relaxed`), given the `old` value and the operand `arg`.
+///
+/// The result is not wrapped to the type of the value. Returns `None`, if `op` isn't a
+/// read-modify-write operation.
+fn atomic_rmw(op: &str, old: &str, arg: &str) -> Option<String> {
+    match op {
+        "xadd" => Some(format!("{}+({})", old, arg)),
+        "xsub" => Some(format!("{}-({})", old, arg)),
+        "and" => Some(format!("{}&({})", old, arg)),
+        "nand" => Some(format!("~({}&({}))", old, arg)),
+        "or" => Some(format!("{}|({})", old, arg)),
+        "xor" => Some(format!("{}^({})", old, arg)),
+        "max" | "umax" => Some(format!("Math.max({},{})", old, arg)),
+        "min" | "umin" => Some(format!("Math.min({},{})", old, arg)),
+        _ => None,
+    }
+}
+
+/// Get the JavaScript tuple of the wrapped result `res` of the checked operation `binop` on the
+/// integers `x` and `y` of the given signedness and bits, and whether it overflowed.
+fn checked_binop(binop: repr::BinOp, signed: bool, bits: u32, res: &str, x: &str, y: &str) -> String {
+    let wrapped = match binop {
+        // Overflowing products of 32-bit integers lose their low bits, so these are wrapped by
+        // `Math.imul` instead. The overflow check still holds, as such products are out of range.
+        repr::BinOp::Mul if bits == 32 => wrap(signed, bits).replace('v', &imul(x, y)),
+        _ => wrap(signed, bits),
+    };
+    // Shifts overflow when the shift amount is too big, rather than by the value.
+    let overflow = match binop {
+        repr::BinOp::Shl | repr::BinOp::Shr => format!("({})>={}", y, bits),
+        // 64-bit integers can't be wrapped exactly, so these are range checked. The maximal
+        // values aren't numbers, so the bounds are the powers of two above them.
+        _ if bits == 64 && signed => "v< -9223372036854775808||v>=9223372036854775808".to_string(),
+        _ if bits == 64 => "v<0||v>=18446744073709551616".to_string(),
+        _ => "w!==v".to_string(),
+    };
+
+    format!("function(v){{var w={};return[w,{}]}}({})", wrapped, overflow, res)
+}
+
+/// Get the JavaScript multiplying the 32-bit integers `x` and `y`, wrapping the product.
+fn imul(x: &str, y: &str) -> String {
+    format!("Math.imul({},{})", x, y)
+}
+
+/// Get the JavaScript advancing the range behind the reference `range`.
+fn range_next(range: &str) -> String {
+    format!("__cyano_range_next({}.get())", range)
+}
+
+/// Get the JavaScript of the comparison method `path` (e.g. `core::cmp::PartialOrd::lt`) of the
+/// strings `a` and `b`, if it is one.
+fn str_cmp(path: &str, a: &str, b: &str) -> Option<String> {
+    let cmp = format!("__cyano_cmp_str({},{})", a, b);
+
+    match path {
+        "core::cmp::PartialEq::eq" => Some(format!("{}==={}", a, b)),
+        "core::cmp::PartialEq::ne" => Some(format!("{}!=={}", a, b)),
+        "core::cmp::PartialOrd::lt" => Some(format!("{}<0", cmp)),
+        "core::cmp::PartialOrd::le" => Some(format!("{}<=0", cmp)),
+        "core::cmp::PartialOrd::gt" => Some(format!("{}>0", cmp)),
+        "core::cmp::PartialOrd::ge" => Some(format!("{}>=0", cmp)),
+        // The discriminants of `Ordering` are -1, 0 and 1, like the result of the helper.
+        "core::cmp::Ord::cmp" => Some(format!("{{d:{}}}", cmp)),
+        "core::cmp::PartialOrd::partial_cmp" => Some(format!("{{d:1,f0:{{d:{}}}}}", cmp)),
+        _ => None,
+    }
+}
+
+/// Get the JavaScript of an iterator over the slice reference `slice`.
+fn slice_iter(slice: &str) -> String {
+    format!("__cyano_slice_iter({})", slice)
+}
+
+/// Get the JavaScript advancing the slice iterator behind the reference `iter`.
+fn slice_iter_next(iter: &str) -> String {
+    format!("__cyano_slice_iter_next({}.get())", iter)
+}
+
+/// Get the JavaScript cloning the elements of the slice reference `src` into `dst`, which are
+/// copied as is if `scalar`.
+fn clone_from_slice(dst: &str, src: &str, scalar: bool) -> String {
+    format!("__cyano_clone_from_slice({},{},{});", dst, src, scalar)
+}
+
+/// Get the JavaScript splitting the slice reference `slice` at the index `mid`.
+fn split_at(slice: &str, mid: &str) -> String {
+    format!("__cyano_split_at({},{})", slice, mid)
+}
+
+/// Get the JavaScript of the raw representation (`core::raw::Slice`) of the slice reference
+/// `slice`.
+///
+/// The JS array carries its length itself, so the data pointer is the reference to the array.
+fn slice_repr(slice: &str) -> String {
+    format!("{{d:0,f0:{0},f1:{0}.get().length}}", slice)
+}
+
+/// Get the signedness and the number of bits of the integer type `ty`, if it is one.
+///
+/// Pointer-sized integers are taken to be 32 bits, like the length of JavaScript arrays.
+fn int_bits(ty: ty::Ty) -> Option<(bool, u32)> {
+    match ty.sty {
+        ty::TyInt(IntTy::I8) => Some((true, 8)),
+        ty::TyInt(IntTy::I16) => Some((true, 16)),
+        ty::TyInt(IntTy::I32) | ty::TyInt(IntTy::Is) => Some((true, 32)),
+        ty::TyInt(IntTy::I64) => Some((true, 64)),
+        ty::TyUint(UintTy::U8) => Some((false, 8)),
+        ty::TyUint(UintTy::U16) => Some((false, 16)),
+        ty::TyUint(UintTy::U32) | ty::TyUint(UintTy::Us) => Some((false, 32)),
+        ty::TyUint(UintTy::U64) => Some((false, 64)),
+        _ => None,
+    }
+}
+
+/// Get the JavaScript reversing the bytes of the integer expression `js` of type `ty`.
+fn bswap(ty: ty::Ty, js: &str) -> String {
+    let (signed, bits) = int_bits(ty).unwrap_or((false, 64));
+    bswap_int(signed, bits, js)
+}
+
+/// Get the JavaScript reversing the bytes of the integer expression `js` of the given signedness
+/// and bits.
+fn bswap_int(signed: bool, bits: u32, js: &str) -> String {
+    let swapped = format!("__cyano_bswap({},{})", js, bits / 8);
+
+    match (signed, bits) {
+        (false, _) => swapped,
+        // 64-bit integers can't be wrapped by bitwise operators, so the sign is applied by hand.
+        (true, 64) => format!("function(v){{return v>=9223372036854775808?v-18446744073709551616:v}}({})", swapped),
+        (true, _) => wrap(signed, bits).replace('v', &swapped),
+    }
+}
+
+/// Coerce the JavaScript expression `js` to the numeric type `ty`, like asm.js annotates it.
+///
+/// Integers of up to 32 bits are wrapped (which is `|0` for `i32`), and floats are marked by `+`
+/// or rounded by `Math.fround`. Other types, including 64-bit integers, which asm.js has no
+/// representation of, are left as is.
+fn coerce(ty: ty::Ty, js: &str) -> String {
+    match ty.sty {
+        ty::TyFloat(FloatTy::F64) => format!("+({})", js),
+        ty::TyFloat(FloatTy::F32) => format!("Math.fround({})", js),
+        _ => match int_bits(ty) {
+            Some((signed, bits)) if bits <= 32 => wrap(signed, bits).replace('v', &format!("({})", js)),
+            _ => js.to_string(),
+        },
+    }
+}
+
+/// Get the size in bytes of the primitive type `ty`, if it is one.
+fn prim_size(ty: ty::Ty) -> Option<u32> {
+    match ty.sty {
+        ty::TyBool => Some(1),
+        ty::TyChar => Some(4),
+        ty::TyFloat(FloatTy::F32) => Some(4),
+        ty::TyFloat(FloatTy::F64) => Some(8),
+        _ => int_bits(ty).map(|(_, bits)| bits / 8),
+    }
+}
+
+/// Get the JavaScript of the call of the slice accessor `method` (e.g. `len`) with the arguments
+/// `args`, the first of which is the slice.
+///
+/// Returns `None` if `method` isn't an accessor of the length or the elements.
+fn slice_accessor(method: &str, args: &[String]) -> Option<String> {
+    match method {
+        "len" => Some(format!("{}.get().length", args[0])),
+        "is_empty" => Some(format!("{}.get().length===0", args[0])),
+        "get" | "get_mut" => Some(format!("__cyano_slice_get({},{})", args[0], args[1])),
+        "first" | "first_mut" => Some(format!("__cyano_slice_get({},0)", args[0])),
+        "last" | "last_mut" => Some(format!("function(s){{return __cyano_slice_get(s,s.get().length-1)}}({})", args[0])),
+        _ => None,
+    }
+}
+
+/// Get the JavaScript indexing the slice `slice` by `range`, a range of the type at `path`.
+///
+/// Returns `None` if the type isn't a range of libcore.
+fn slice_index(path: &str, slice: &str, range: &str) -> Option<String> {
+    let bounds = match path {
+        "core::ops::Range" => format!("{0}.f0,{0}.f1", range),
+        "core::ops::RangeTo" => format!("0,{}.f0", range),
+        "core::ops::RangeFrom" => format!("{}.f0", range),
+        "core::ops::RangeFull" => "0".to_string(),
+        _ => return None,
+    };
+
+    Some(format!("__cyano_slice_index({},{})", slice, bounds))
+}
+
+/// Get the JavaScript of the integer division or remainder `binop` of `x` by `y`.
+///
+/// Integer division must round towards zero, whereas the JS remainder already matches Rust's.
+fn int_div(binop: repr::BinOp, x: &str, y: &str) -> String {
+    if binop == repr::BinOp::Div {
+        format!("Math.trunc(({})/({}))", x, y)
+    } else {
+        format!("({})%({})", x, y)
+    }
+}
+
+/// Get the JavaScript of the integer division or remainder `res` by `y`, trapping if `y` is zero.
+///
+/// JavaScript gives `Infinity` or `NaN` on zero divisors, so we trap instead.
+fn check_divisor(binop: repr::BinOp, y: &str, res: &str) -> String {
+    let msg = if binop == repr::BinOp::Div {
+        "attempt to divide by zero"
+    } else {
+        "attempt to calculate the remainder with a divisor of zero"
+    };
+
+    format!("({})===0?__cyano_trap(\"{}\"):{}", y, msg, res)
+}
+
+/// Get the size and alignment of an aggregate with fields of the sizes and alignments `fields`.
+///
+/// The fields are laid out by decreasing alignment, so only the end is padded.
+fn aggregate_layout(fields: &[(u32, u32)]) -> (u32, u32) {
+    let size = fields.iter().map(|&(size, _)| size).sum::<u32>();
+    let align = fields.iter().map(|&(_, align)| align).max().unwrap_or(1);
+
+    ((size + align - 1) / align * align, align)
+}
+
+/// Get the JavaScript expression wrapping the integer `v` to the given signedness and bits.
+///
+/// 64-bit integers are beyond the exact range of JavaScript numbers, so these are left as is.
+fn wrap(signed: bool, bits: u32) -> String {
+    match (signed, bits) {
+        (_, 64) => "v".to_string(),
+        (false, 32) => "v>>>0".to_string(),
+        (true, 32) => "v|0".to_string(),
+        (false, bits) => format!("v&{}", (1u32 << bits) - 1),
+        (true, bits) => format!("v<<{0}>>{0}", 32 - bits),
+    }
+}
+
+/// Is the block `data` merely returning?
+fn is_return(data: &repr::BasicBlockData) -> bool {
+    data.statements.iter().all(|i| match i.kind {
+        repr::StatementKind::StorageLive(_) | repr::StatementKind::StorageDead(_) => true,
+        _ => false,
+    }) && match data.terminator.as_ref().map(|x| &x.kind) {
+        Some(&repr::TerminatorKind::Return) => true,
+        _ => false,
+    }
+}
+
+/// Is the block `data` unreachable, doing nothing at all?
+fn is_unreachable(data: &repr::BasicBlockData) -> bool {
+    match (data.statements.is_empty(), data.terminator.as_ref().map(|x| &x.kind)) {
+        (true, Some(&repr::TerminatorKind::Unreachable)) => true,
+        _ => false,
+    }
+}
+
+/// Get the JavaScript declaring the locals of a function with `vars` variables and `temps`
+/// temporaries, along with the return variable and the goto state, by `let` or `var`.
+fn local_decls(let_declarations: bool, vars: usize, temps: usize) -> String {
+    let mut js = format!("{} g=0,r", if let_declarations { "let" } else { "var" });
+    for var in 0..vars {
+        js.push_str(&format!(",{}", codegen::Var(repr::Var::new(var))));
+    }
+    for temp in 0..temps {
+        js.push_str(&format!(",{}", codegen::Tmp(repr::Temp::new(temp))));
+    }
+    js.push(';');
+
+    js
+}
+
+/// Get the JavaScript reassigning the `n` arguments of the function being written to `args`.
+///
+/// The arguments are all evaluated before any of them is reassigned, using the (yet unused) return
+/// variable as scratch.
+fn tail_jump(args: &str, n: usize) -> String {
+    let mut js = format!("r=[{}];", args);
+    for i in 0..n {
+        js.push_str(&format!("{}=r[{}];", codegen::Arg(repr::Arg::new(i)), i));
+    }
+
+    js
+}
+
+/// Does `lvalue` read a local, be it as its base or as an index?
+fn reads_local(lvalue: &repr::Lvalue) -> bool {
+    match lvalue {
+        &repr::Lvalue::Static(_) => false,
+        &repr::Lvalue::Projection(box repr::Projection { ref base, elem: repr::ProjectionElem::Index(ref index) }) =>
+            reads_local(base) || match index {
+                &repr::Operand::Consume(ref index) => reads_local(index),
+                &repr::Operand::Constant(_) => false,
+            },
+        &repr::Lvalue::Projection(box repr::Projection { ref base, .. }) => reads_local(base),
+        _ => true,
+    }
+}
+
+/// Is `lvalue` an argument, or a field or dereference path rooted in one?
+fn is_arg_path(lvalue: &repr::Lvalue) -> bool {
+    match lvalue {
+        &repr::Lvalue::Arg(_) => true,
+        &repr::Lvalue::Projection(box repr::Projection { ref base, elem: repr::ProjectionElem::Deref })
+        | &repr::Lvalue::Projection(box repr::Projection { ref base, elem: repr::ProjectionElem::Field(..) }) =>
+            is_arg_path(base),
+        _ => false,
+    }
+}
+
+/// Is `lvalue` a local, or a field path (through variants as well) rooted in one?
+fn is_local_field_path(lvalue: &repr::Lvalue) -> bool {
+    match lvalue {
+        &repr::Lvalue::Var(_) | &repr::Lvalue::Temp(_) => true,
+        // By-value bindings of matches move out of the fields of a variant.
+        &repr::Lvalue::Projection(box repr::Projection { ref base, elem: repr::ProjectionElem::Field(..) })
+        | &repr::Lvalue::Projection(box repr::Projection { ref base, elem: repr::ProjectionElem::Downcast(..) }) =>
+            is_local_field_path(base),
+        _ => false,
+    }
+}
+
+/// Is `location` (or a field of it) among the lvalues `moved` out of?
+fn is_moved<'a>(moved: &[repr::Lvalue<'a>], location: &repr::Lvalue<'a>) -> bool {
+    // A partially moved struct still holds the moved fields, so it is left alone as a whole.
+    moved.iter().any(|lvalue| lvalue == location || is_field_of(lvalue, location))
+}
+
+/// Is `lvalue` a (possibly nested) field of `parent`?
+fn is_field_of(lvalue: &repr::Lvalue, parent: &repr::Lvalue) -> bool {
+    match lvalue {
+        &repr::Lvalue::Projection(box repr::Projection { ref base, elem: repr::ProjectionElem::Field(..) })
+        | &repr::Lvalue::Projection(box repr::Projection { ref base, elem: repr::ProjectionElem::Downcast(..) }) =>
+            base == parent || is_field_of(base, parent),
+        _ => false,
+    }
+}
+
+/// Substitute the arguments of the argument path `lvalue` by the operands `args`.
+///
+/// This fails if a substituted argument is a constant, as it can't be projected.
+fn subst_args<'a>(lvalue: &repr::Lvalue<'a>, args: &[repr::Operand<'a>]) -> Option<repr::Lvalue<'a>> {
+    match lvalue {
+        &repr::Lvalue::Arg(arg) => match args[arg.index()] {
+            repr::Operand::Consume(ref lvalue) => Some(lvalue.clone()),
+            repr::Operand::Constant(_) => None,
+        },
+        &repr::Lvalue::Projection(box repr::Projection { ref base, ref elem }) =>
+            subst_args(base, args).map(|base| repr::Lvalue::Projection(Box::new(repr::Projection {
+                base: base,
+                elem: elem.clone(),
+            }))),
+        _ => None,
+    }
+}
+
+/// A MIR visitor collecting the items referred to by literals, other than callees, and the
+/// literals, which may be hoisted.
+///
+/// Callees are scheduled by the calls themselves, as their type arguments depend on the
+/// instance, and some calls (e.g. to intrinsics) don't refer to the callee at all.
+struct ItemCollector<'a> {
+    tcx: TyCtxt<'a, 'a, 'a>,
+    /// Every item referred to, along with its type arguments.
+    items: Vec<(DefId, &'a Substs<'a>)>,
+    /// The string literals and the arrays of constant numbers, as written in JavaScript. Raw
+    /// JavaScript is left out.
+    literals: Vec<String>,
+}
+
+impl<'a> Visitor<'a> for ItemCollector<'a> {
+    fn visit_literal(&mut self, literal: &repr::Literal<'a>) {
+        match literal {
+            &repr::Literal::Item { def_id, substs } => self.items.push((def_id, substs)),
+            &repr::Literal::Value { value: ConstVal::Function(def_id) } =>
+                self.items.push((def_id, Substs::empty(self.tcx))),
+            &repr::Literal::Value { value: ConstVal::Str(_) } => {
+                let js = codegen::Literal(literal).to_string();
+                if js.starts_with('"') {
+                    self.literals.push(js);
+                }
+            },
+            _ => (),
+        }
+    }
+
+    fn visit_rvalue(&mut self, rvalue: &repr::Rvalue<'a>) {
+        // Arrays of strings are left to the hoisting of their elements.
+        let is_number = |operand: &repr::Operand| match operand {
+            &repr::Operand::Constant(repr::Constant { literal: repr::Literal::Value { ref value }, .. }) => match value {
+                &ConstVal::Integral(_) | &ConstVal::Float(_) | &ConstVal::Bool(_) | &ConstVal::Char(_) => true,
+                _ => false,
+            },
+            _ => false,
+        };
+
+        match rvalue {
+            &repr::Rvalue::Aggregate(repr::AggregateKind::Vec, ref elems) if elems.iter().all(&is_number) =>
+                self.literals.push(codegen::Rvalue(rvalue).to_string()),
+            &repr::Rvalue::Repeat(ref elem, _) if is_number(elem) =>
+                self.literals.push(codegen::Rvalue(rvalue).to_string()),
+            _ => (),
+        }
+
+        self.super_rvalue(rvalue);
+    }
+
+    fn visit_terminator_kind(&mut self, block: repr::BasicBlock, kind: &repr::TerminatorKind<'a>) {
+        if let &repr::TerminatorKind::Call {
+            func: repr::Operand::Constant(repr::Constant { literal: repr::Literal::Item { .. }, .. }),
+            ref args,
+            ..
+        } = kind {
+            // Skip the callee.
+            for i in args {
+                self.visit_operand(i);
+            }
+        } else {
+            self.super_terminator_kind(block, kind);
+        }
+    }
+}
+
+/// A MIR visitor collecting the locals, which are moved out of.
+///
+/// Fields moved out of locals (partial moves, e.g. by struct update syntax) are collected as well,
+/// unless they are merely copied, as the local is still dropped as a whole then. Consuming a local
+/// of a `Copy` type merely copies it too, but such types never have drop glue, so it makes no
+/// difference that these are collected.
+struct MoveCollector<'a, F> {
+    moved: Vec<repr::Lvalue<'a>>,
+    /// Is consuming the given field a move (i.e. is its type not `Copy`)?
+    moves: F,
+}
+
+impl<'a, F: Fn(&repr::Lvalue<'a>) -> bool> Visitor<'a> for MoveCollector<'a, F> {
+    fn visit_operand(&mut self, operand: &repr::Operand<'a>) {
+        match operand {
+            &repr::Operand::Consume(ref lvalue) if is_local_field_path(lvalue) =>
+                if !self.moved.contains(lvalue) && match lvalue {
+                    &repr::Lvalue::Projection(_) => (self.moves)(lvalue),
+                    _ => true,
+                } {
+                    self.moved.push(lvalue.clone());
+                },
+            _ => (),
+        }
+
+        self.super_operand(operand);
+    }
+}
+
+/// A MIR visitor finding references to locals.
+#[derive(Default)]
+struct LocalBorrows {
+    found: bool,
+}
+
+impl<'a> Visitor<'a> for LocalBorrows {
+    fn visit_rvalue(&mut self, rvalue: &repr::Rvalue<'a>) {
+        if let &repr::Rvalue::Ref(_, _, ref lvalue) = rvalue {
+            self.found |= reads_local(lvalue);
+        }
+
+        self.super_rvalue(rvalue);
+    }
+}
+
+/// A MIR visitor rewriting the field projections of tuples to indices.
+///
+/// Tuples (and closure environments) are represented by arrays, so their fields are read by
+/// indexing rather than by field name.
+struct TupleFields<'b, 'a: 'b> {
+    tcx: TyCtxt<'a, 'a, 'a>,
+    body: &'b repr::Mir<'a>,
+}
+
+impl<'b, 'a> MutVisitor<'a> for TupleFields<'b, 'a> {
+    fn visit_lvalue(&mut self, lvalue: &mut repr::Lvalue<'a>, context: LvalueContext) {
+        // The type of the base must be found before its own projections are rewritten.
+        let field = match lvalue {
+            &mut repr::Lvalue::Projection(box repr::Projection {
+                ref base,
+                elem: repr::ProjectionElem::Field(field, _),
+            }) => match base.ty(self.body, self.tcx).to_ty(self.tcx).sty {
+                ty::TyTuple(_) | ty::TyClosure(..) => Some(field),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        self.super_lvalue(lvalue, context);
+
+        if let (Some(field), &mut repr::Lvalue::Projection(ref mut proj)) = (field, lvalue) {
+            proj.elem = repr::ProjectionElem::Index(repr::Operand::Constant(repr::Constant {
+                span: DUMMY_SP,
+                ty: self.tcx.types.usize,
+                literal: repr::Literal::Value {
+                    value: ConstVal::Integral(ConstInt::Infer(field.index() as u64)),
+                },
+            }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use config::Target;
+    use rustc::hir::def_id::{DefId, DefIndex};
+    use rustc::mir::repr::{Arg, BasicBlockData, BinOp, Lvalue, Operand, Projection, ProjectionElem, Rvalue, SourceInfo,
+                           Statement, StatementKind, Temp, Terminator, TerminatorKind, Var, ARGUMENT_VISIBILITY_SCOPE,
+                           START_BLOCK};
+    use rustc::mir::visit::Visitor;
+    use rustc_data_structures::indexed_vec::Idx;
+    use runtime;
+    use syntax::codemap::DUMMY_SP;
+
+    #[test]
+    fn test_aggregate_layout() {
+        assert_eq!(aggregate_layout(&[]), (0, 1));
+        assert_eq!(aggregate_layout(&[(0, 1), (0, 1)]), (0, 1));
+        assert_eq!(aggregate_layout(&[(1, 1), (8, 8)]), (16, 8));
+        assert_eq!(aggregate_layout(&[(4, 4), (2, 2), (1, 1)]), (8, 4));
+        assert_eq!(aggregate_layout(&[(12, 4), (1, 1)]), (16, 4));
+    }
+
+    #[test]
+    fn test_atomic_rmw() {
+        assert_eq!(atomic_rmw("xadd", "x", "1").unwrap(), "x+(1)");
+        assert_eq!(atomic_rmw("nand", "x", "y").unwrap(), "~(x&(y))");
+        assert_eq!(atomic_rmw("umin", "x", "y").unwrap(), "Math.min(x,y)");
+        assert!(atomic_rmw("fence", "x", "y").is_none());
+        assert!(atomic_rmw("cxchg", "x", "y").is_none());
+    }
+
+    #[test]
+    fn test_atomic_counter() {
+        // An `AtomicUsize` counter, as `fetch_add`, `fetch_sub`, `fetch_max` and
+        // `compare_and_swap` are lowered.
+        let fetch = |op: &str, arg: &str| format!("(function(){{var o=c.get();c.set(({})>>>0);return o}})()",
+                                      atomic_rmw(op, "o", arg).unwrap());
+        let js = format!("var n=0,c={{get:function(){{return n}},set:function(x){{n=x}}}},olds=[];\
+                          for(var i=0;i<10;i++)olds.push({});\
+                          olds.push({},{},{});\
+                          console.log(olds.join(),n,JSON.stringify({}),JSON.stringify({}),n)",
+                         fetch("xadd", "1"), fetch("xsub", "3"), fetch("max", "100"), fetch("xsub", "101"),
+                         atomic_cxchg("c", "5", "7"), atomic_cxchg("c", "4294967295", "8"));
+        assert_eq!(runtime::eval(&js), "0,1,2,3,4,5,6,7,8,9,10,7,100 4294967295 [4294967295,false] \
+                                        [4294967295,true] 8\n");
+    }
+
+    #[test]
+    fn test_checked_binop() {
+        let mul = |signed, bits, x: &str, y: &str| {
+            let res = format!("({})*({})", x, y);
+            format!("JSON.stringify({})", checked_binop(BinOp::Mul, signed, bits, &res, x, y))
+        };
+        let add = |signed, bits, x: &str, y: &str| {
+            let res = format!("({})+({})", x, y);
+            format!("JSON.stringify({})", checked_binop(BinOp::Add, signed, bits, &res, x, y))
+        };
+
+        let js = format!("console.log([{}].join(' '))", [
+            mul(true, 32, "3", "-4"),
+            mul(true, 32, "65536", "65536"),
+            mul(false, 32, "4294967295", "4294967295"),
+            mul(true, 32, "123456789", "987654321"),
+            add(false, 8, "255", "1"),
+            add(true, 64, "9223372036854775807", "1"),
+            add(true, 64, "-9223372036854775807", "-1"),
+            add(false, 64, "18446744073709551615", "1"),
+            add(false, 64, "0", "-1"),
+        ].join(","));
+        assert_eq!(runtime::eval(&js), "[-12,false] [0,true] [1,true] [-67153019,true] [0,true] \
+                                        [9223372036854776000,true] [-9223372036854776000,false] \
+                                        [18446744073709552000,true] [-1,true]\n");
+    }
+
+    #[test]
+    fn test_move_collector() {
+        // Only fields are checked for being copied, as whole locals of `Copy` types have no drop
+        // glue anyway.
+        let mut moves = MoveCollector {
+            moved: Vec::new(),
+            moves: |_: &Lvalue| false,
+        };
+        moves.visit_operand(&Operand::Consume(Lvalue::Var(Var::new(0))));
+        moves.visit_operand(&Operand::Consume(Lvalue::Temp(Temp::new(1))));
+        moves.visit_operand(&Operand::Consume(Lvalue::Var(Var::new(0))));
+        moves.visit_operand(&Operand::Consume(Lvalue::Arg(Arg::new(0))));
+
+        assert_eq!(moves.moved, [Lvalue::Var(Var::new(0)), Lvalue::Temp(Temp::new(1))]);
+    }
+
+    #[test]
+    fn test_is_moved() {
+        // `let t = v0;` moves `v0` to its new owner, which drops it instead, whereas `v1` is
+        // dropped where it goes out of scope.
+        let mut moves = MoveCollector {
+            moved: Vec::new(),
+            moves: |_: &Lvalue| true,
+        };
+        moves.visit_rvalue(&Rvalue::Use(Operand::Consume(Lvalue::Var(Var::new(0)))));
+
+        assert!(is_moved(&moves.moved, &Lvalue::Var(Var::new(0))));
+        assert!(!is_moved(&moves.moved, &Lvalue::Var(Var::new(1))));
+        assert!(!is_moved(&[], &Lvalue::Var(Var::new(0))));
+    }
+
+    #[test]
+    fn test_is_local_field_path() {
+        // Field and variant projections take their types and enum definitions from the type
+        // context, so only the paths, which don't move out of the local, are built here.
+        let project = |base, elem| Lvalue::Projection(Box::new(Projection {
+            base: base,
+            elem: elem,
+        }));
+
+        assert!(is_local_field_path(&Lvalue::Var(Var::new(0))));
+        assert!(is_local_field_path(&Lvalue::Temp(Temp::new(0))));
+        // Dereferences and indices lead out of the local.
+        assert!(!is_local_field_path(&project(Lvalue::Var(Var::new(0)), ProjectionElem::Deref)));
+        assert!(!is_local_field_path(&project(Lvalue::Var(Var::new(0)),
+                                              ProjectionElem::Index(Operand::Consume(Lvalue::Arg(Arg::new(0)))))));
+        assert!(!is_local_field_path(&Lvalue::Arg(Arg::new(0))));
+        assert!(!is_field_of(&project(Lvalue::Var(Var::new(0)), ProjectionElem::Deref), &Lvalue::Var(Var::new(0))));
+    }
+
+    #[test]
+    fn test_bswap() {
+        let swaps = [
+            // `0x01020304u32.to_be()` on a little-endian target.
+            bswap_int(false, 32, "16909060"),
+            bswap_int(true, 32, "128"),
+            bswap_int(true, 16, "-2"),
+            bswap_int(false, 8, "171"),
+            // `0x80i64.swap_bytes()`.
+            bswap_int(true, 64, "128"),
+            bswap_int(true, 64, "-9223372036854775808"),
+            bswap_int(false, 64, "255"),
+        ];
+        let js = format!("console.log([{}].join(' '))", swaps.join(","));
+        assert_eq!(runtime::eval(&js), "67305985 -2147483648 -257 171 -9223372036854776000 128 \
+                                        18374686479671624000\n");
+    }
+
+    #[test]
+    fn test_int_div() {
+        let div = |binop, x: &str, y: &str| check_divisor(binop, y, &int_div(binop, x, y));
+        let js = format!("console.log({},{},{},{});\
+                          try{{{}}}catch(e){{console.log(e.message)}}\
+                          try{{{}}}catch(e){{console.log(e.message)}}",
+                         div(BinOp::Div, "-7", "2"), div(BinOp::Rem, "-7", "2"), div(BinOp::Div, "7", "-2"),
+                         div(BinOp::Rem, "7", "-2"), div(BinOp::Div, "1", "0"), div(BinOp::Rem, "1", "0"));
+        assert_eq!(runtime::eval(&js), "-3 -1 -3 1\nattempt to divide by zero\n\
+                                        attempt to calculate the remainder with a divisor of zero\n");
+    }
+
+    #[test]
+    fn test_is_return() {
+        let source_info = SourceInfo {
+            span: DUMMY_SP,
+            scope: ARGUMENT_VISIBILITY_SCOPE,
+        };
+        let block = |kinds: Vec<StatementKind<'static>>, kind| {
+            let mut data = BasicBlockData::new(Some(Terminator {
+                source_info: source_info,
+                kind: kind,
+            }));
+            data.statements = kinds.into_iter().map(|kind| Statement {
+                source_info: source_info,
+                kind: kind,
+            }).collect();
+            data
+        };
+
+        assert!(is_return(&block(Vec::new(), TerminatorKind::Return)));
+        assert!(is_return(&block(vec![StatementKind::StorageDead(Lvalue::Var(Var::new(0))),
+                                      StatementKind::StorageDead(Lvalue::Temp(Temp::new(1)))],
+                                 TerminatorKind::Return)));
+        // The return value is assigned, so jumping here must not skip the block.
+        assert!(!is_return(&block(vec![StatementKind::Assign(Lvalue::ReturnPointer,
+                                                             Rvalue::Use(Operand::Consume(Lvalue::Var(Var::new(0)))))],
+                                  TerminatorKind::Return)));
+        assert!(!is_return(&block(Vec::new(), TerminatorKind::Goto { target: START_BLOCK })));
+        assert!(!is_return(&block(Vec::new(), TerminatorKind::Unreachable)));
+    }
+
+    #[test]
+    fn test_unreachable_otherwise() {
+        let source_info = SourceInfo {
+            span: DUMMY_SP,
+            scope: ARGUMENT_VISIBILITY_SCOPE,
+        };
+        let block = |kind| BasicBlockData::new(Some(Terminator {
+            source_info: source_info,
+            kind: kind,
+        }));
+        let mut storage_dead = block(TerminatorKind::Unreachable);
+        storage_dead.statements.push(Statement {
+            source_info: source_info,
+            kind: StatementKind::StorageDead(Lvalue::Var(Var::new(0))),
+        });
+
+        assert!(is_unreachable(&block(TerminatorKind::Unreachable)));
+        assert!(!is_unreachable(&storage_dead));
+        assert!(!is_unreachable(&block(TerminatorKind::Return)));
+
+        // A match on a two-variant enum, whose value is corrupt, and an exhaustive match on a `u8`
+        // lowered to a jump table of the values from 0 to 1.
+        let js = format!("var v0={{d:7}},t0=9,g;\
+                          try{{switch(v0.d){{case 0:case 1:g=1;break;default:{}}}}}catch(e){{console.log(e.message)}}\
+                          try{{g=[1,2][t0-(0)];if(g===undefined){{{}}}}}catch(e){{console.log(e.message)}}",
+                         INVALID_DISCRIMINANT, NON_EXHAUSTIVE);
+        assert_eq!(runtime::eval(&js), "Invalid enum discriminant.\nNon-exhaustive switch.\n");
+    }
+
+    #[test]
+    fn test_local_decls() {
+        assert_eq!(local_decls(false, 0, 0), "var g=0,r;");
+        assert_eq!(local_decls(true, 2, 1), "let g=0,r,v0,v1,t0;");
+
+        // A temporary assigned by a later block, but read by an earlier one, as a loop does.
+        let js = format!("function f(){{{}t:while(true){{switch(g){{\
+                          case 0:if(v0===undefined){{g=1;continue t}}r=v0+t0;return r;\
+                          case 1:v0=1;t0=2;g=0;continue t;}}}}}}console.log(f())", local_decls(true, 1, 1));
+        assert_eq!(runtime::eval(&js), "3\n");
+    }
+
+    #[test]
+    fn test_slice_accessor() {
+        let call = |method: &str, args: &[&str]| {
+            let args: Vec<_> = args.iter().map(|x| x.to_string()).collect();
+            slice_accessor(method, &args).unwrap()
+        };
+        // libcore's `Option` is `None` (0) or `Some` (1).
+        let js = format!("var a=[4,5,6],s={{get:function(){{return a}}}},e={{get:function(){{return[]}}}};\
+                          {}.f0.set(7);\
+                          console.log(JSON.stringify([{},{},{},{},{}.f0.get(),{}.f0.get(),{}.d,{}.d,{}.d,a]))",
+                         call("get_mut", &["s", "1"]), call("len", &["s"]), call("is_empty", &["s"]),
+                         call("is_empty", &["e"]), call("len", &["e"]), call("first", &["s"]), call("last", &["s"]),
+                         call("get", &["s", "3"]), call("first", &["e"]), call("last", &["e"]));
+        assert_eq!(runtime::eval(&js), "[3,false,true,0,4,6,0,0,0,[4,7,6]]\n");
+        assert!(slice_accessor("split_at", &["s".to_string()]).is_none());
+    }
+
+    #[test]
+    fn test_slice_index() {
+        let index = |path: &str| slice_index(path, "s", "x").unwrap();
+        let js = format!("var a=[1,2,3,4,5],s={{get:function(){{return a}}}},x={{f0:1,f1:3}};\
+                          console.log([{},{},{},{}].map(function(v){{return v.get().join('')}}).join(' '))",
+                         index("core::ops::Range"), index("core::ops::RangeTo"), index("core::ops::RangeFrom"),
+                         index("core::ops::RangeFull"));
+        assert_eq!(runtime::eval(&js), "23 1 2345 12345\n");
+        assert!(slice_index("core::ops::RangeInclusive", "s", "x").is_none());
+    }
+
+    #[test]
+    fn test_subst_args() {
+        let deref = |base| Lvalue::Projection(Box::new(Projection {
+            base: base,
+            elem: ProjectionElem::Deref,
+        }));
+        // The getter `fn get(&self) -> &T { &*self.0 }` reads `*a0`, and is called with `t3`.
+        let read = deref(Lvalue::Arg(Arg::new(0)));
+        let args = [Operand::Consume(Lvalue::Temp(Temp::new(3)))];
+
+        assert!(is_arg_path(&read));
+        assert!(is_arg_path(&Lvalue::Arg(Arg::new(1))));
+        assert!(!is_arg_path(&deref(Lvalue::Var(Var::new(0)))));
+        assert!(!is_arg_path(&Lvalue::ReturnPointer));
+
+        assert_eq!(subst_args(&read, &args), Some(deref(Lvalue::Temp(Temp::new(3)))));
+        assert_eq!(subst_args(&Lvalue::Var(Var::new(0)), &args), None);
+    }
+
+    #[test]
+    fn test_tail_jump() {
+        assert_eq!(tail_jump("a1,a0", 2), "r=[a1,a0];a0=r[0];a1=r[1];");
+        assert_eq!(tail_jump("", 0), "r=[];");
+
+        // A countdown far deeper than the stack, written like a compiled function.
+        let js = format!("function f(a0,a1){{var r;var g=0;t:while(true){{switch(g){{\
+                          case 0:if(a0===0){{g=1;continue t}}{}g=0;continue t;\
+                          case 1:r=a1;return r;}}}}}}\
+                          console.log(f(1000000,0))", tail_jump("a0-1,a1+1", 2));
+        assert_eq!(runtime::eval(&js), "1000000\n");
+    }
+
+    #[test]
+    fn test_reads_local() {
+        let index = |base, index| Lvalue::Projection(Box::new(Projection {
+            base: base,
+            elem: ProjectionElem::Index(Operand::Consume(index)),
+        }));
+
+        assert!(reads_local(&Lvalue::Var(Var::new(0))));
+        assert!(reads_local(&Lvalue::Temp(Temp::new(1))));
+        assert!(reads_local(&Lvalue::Arg(Arg::new(0))));
+        assert!(reads_local(&Lvalue::Projection(Box::new(Projection {
+            base: Lvalue::Var(Var::new(0)),
+            elem: ProjectionElem::Deref,
+        }))));
+        assert!(reads_local(&index(Lvalue::Arg(Arg::new(0)), Lvalue::Temp(Temp::new(0)))));
+
+        let global = Lvalue::Static(DefId::local(DefIndex::new(0)));
+        assert!(!reads_local(&global));
+        assert!(reads_local(&index(global, Lvalue::Var(Var::new(1)))));
+    }
+
+    #[test]
+    fn test_wrap() {
+        let wrapped = |signed, bits, v: &str| format!("function(v){{return {}}}({})", wrap(signed, bits), v);
+
+        let js = format!("console.log({})", [
+            wrapped(true, 8, "200"),
+            wrapped(true, 8, "-129"),
+            wrapped(false, 8, "-1"),
+            wrapped(true, 16, "32768"),
+            wrapped(false, 32, "-1"),
+            wrapped(true, 32, "2147483648"),
+            wrapped(true, 64, "-1"),
+        ].join(","));
+        assert_eq!(runtime::eval(&js), "-56 127 255 -32768 4294967295 -2147483648 -1\n");
+    }
+
+    #[test]
+    fn test_program() {
+        // The program as `write_program` lays it out, with a main function exporting itself.
+        let js = format!("{}{}{}function d0_0(){{console.log(typeof __cyano_trap)}}__cyano_ns.main=d0_0;d0_0();{}\
+                          console.log(typeof app.main,typeof d0_0)",
+                         PROGRAM_START, namespace(Target::Node, Some("app")), runtime::helpers(Target::Node).join(""),
+                         PROGRAM_END);
+        assert_eq!(runtime::run(&js), "function\nfunction undefined\n");
+        assert_eq!(namespace(Target::Worker, None), "var __cyano_ns=self;");
+    }
+
+    #[test]
+    fn test_diverging_intrinsic() {
+        let js = format!("try{{{}}}catch(e){{console.log(e.message)}}", diverging_intrinsic("unreachable").unwrap());
+        assert_eq!(runtime::eval(&js), "entered unreachable code\n");
+        assert_eq!(diverging_intrinsic("abort"), Some("__cyano_abort();"));
+        assert!(diverging_intrinsic("breakpoint").is_none());
+    }
+
+    #[test]
+    fn test_virtual_call() {
+        // A `&Shape` trait object, whose `area` method comes from a supertrait.
+        let vtable = vtable_object(&[("name".to_string(), "function(s){return \"square\"}".to_string()),
+                                     ("area".to_string(), "function(s,k){return k*s.get()*s.get()}".to_string())]);
+        let shape = "{data:{get:function(){return 3}},vtable:V}".replace("V", &vtable);
+        let js = format!("var o={};console.log({},{})", shape, virtual_call("name", &["o".to_string()]),
+                         virtual_call("area", &["o".to_string(), "2".to_string()]));
+        assert_eq!(runtime::eval(&js), "square 18\n");
+        assert_eq!(vtable_object(&[]), "{}");
+    }
+
+    #[test]
+    fn test_mul_add() {
+        // (1+2^-12)^2+2^-70 is just above a halfway case between two `f32`s.
+        let (x, z) = ("Math.fround(1+Math.pow(2,-12))", "Math.fround(Math.pow(2,-70))");
+        let js = format!("console.log({},{},{},{})", mul_add(true, true, x, x, z), mul_add(false, true, x, x, z),
+                         mul_add(true, false, "0.1", "10", "-1"), mul_add(false, false, "0.1", "10", "-1"));
+        assert_eq!(runtime::eval(&js), "1.0004884004592896 1.0004883408546448 5.551115123125783e-17 0\n");
+    }
+
+    #[test]
+    fn test_assert() {
+        // The bounds check of `v[i]`, and the overflow check of `x + 1`, whose flag is `t0[1]`.
+        let check = |cond: &str, expected, msg: &str| format!("try{{{}console.log(\"ok\")}}\
+                                                               catch(e){{console.log(e.message)}}",
+                                                              assert(cond, expected, msg));
+        let bounds = bounds_message("v0.length", "a0");
+        assert_eq!(assert("t0", true, "\"m\""), "if(!(t0)){__cyano_trap(\"m\");}");
+        let js = format!("var v0=[1,2,3],a0=5,t0=[0,true];{}{}{}", check("a0<v0.length", true, &bounds),
+                         check("t0[1]", false, "\"attempt to add with overflow\""), check("a0>3", true, &bounds));
+        assert_eq!(runtime::eval(&js), "index out of bounds: len 3, index 5\nattempt to add with overflow\nok\n");
+    }
+
+    #[test]
+    fn test_drop_and_replace() {
+        // `v[i] = x` for a `v: [Box<T>; 3]`, which drops the old box first. Replacing an element
+        // of a `[u32; 3]` merely assigns.
+        let location = Lvalue::Projection(Box::new(Projection {
+            base: Lvalue::Var(Var::new(0)),
+            elem: ProjectionElem::Index(Operand::Consume(Lvalue::Arg(Arg::new(0)))),
+        }));
+        let value = Operand::Consume(Lvalue::Arg(Arg::new(1)));
+        let js = drop_and_replace(&location, &value, true);
+        assert_eq!(js, "delete v0[a0];v0[a0]=a1;");
+        assert_eq!(drop_and_replace(&location, &value, false), "v0[a0]=a1;");
+
+        let js = format!("var v0=[{{}},{{}},{{}}],a0=1,a1={{n:2}};{}console.log(JSON.stringify(v0))", js);
+        assert_eq!(runtime::eval(&js), "[{},{\"n\":2},{}]\n");
+    }
+
+    #[test]
+    fn test_ctor() {
+        // `Some` (1 in libcore's `Option`) as a function value, as in `map(Some)`.
+        let some = codegen::Item(DefId::local(DefIndex::new(0x1f))).to_string();
+        assert_eq!(some, "d1f_0");
+        let js = format!("{}{}{}console.log(JSON.stringify([[3,4].map(function(x){{return d1f_0(x)}}),d2_0(),\
+                          d3_0(1,2)]))",
+                         ctor(&some, "1", 1), ctor("d2_0", "0", 0), ctor("d3_0", "2", 2));
+        assert_eq!(runtime::eval(&js), "[[{\"d\":1,\"f0\":3},{\"d\":1,\"f0\":4}],{\"d\":0},\
+                                        {\"d\":2,\"f0\":1,\"f1\":2}]\n");
+        assert_eq!(ctor("d2_0", "0", 0), "function d2_0(){return{d:0}}");
+    }
+
+    #[test]
+    fn test_impl_instances() {
+        // `impl<T: Into<u32>> Show for Wrapper<T>` resolved for `Wrapper<u8>` and `Wrapper<char>`
+        // is instantiated with the impl's `T`, so each gets its own `show`, named after `T`.
+        let show = |ty: &str| format!("d5_0{}", codegen::TypeArgs(&[ty.to_string()]));
+        let (byte, chr) = (show("u8"), show("char"));
+        assert!(byte != chr);
+        let js = format!("function {0}(a0){{return\"byte \"+a0.get()}}function {1}(a0){{return\"char \"+a0.get()}}\
+                          var a={{get:function(){{return 97}}}};console.log({0}(a),{1}(a))", byte, chr);
+        assert_eq!(runtime::eval(&js), "byte 97 char 97\n");
+    }
+
+    #[test]
+    fn test_int_to_enum() {
+        // `transmute::<u8, Level>(x)` for `enum Level { Low = 1, High = 4 }`.
+        let discrs = ["1".to_string(), "4".to_string()];
+        let js = format!("console.log(JSON.stringify([{},{},{}]));try{{var t0={}}}catch(e){{console.log(e.message)}}",
+                         int_to_enum(true, &discrs, "1"), int_to_enum(true, &discrs, "4"),
+                         int_to_enum(false, &discrs, "2"), int_to_enum(true, &discrs, "2"));
+        assert_eq!(runtime::eval(&js), "[{\"d\":1},{\"d\":4},{\"d\":2}]\ninvalid enum discriminant: 2\n");
+    }
+
+    #[test]
+    fn test_is_fn_trait() {
+        let id = |i| Some(DefId::local(DefIndex::new(i)));
+        let fn_traits = [id(1), id(2), id(3)];
+
+        // `Fn::call`, and `FnOnce::call_once`.
+        assert!(is_fn_trait(id(1), &fn_traits));
+        assert!(is_fn_trait(id(3), &fn_traits));
+        // `Wrapper::<F>::new`, which isn't in a trait, and a method of another trait.
+        assert!(!is_fn_trait(None, &fn_traits));
+        assert!(!is_fn_trait(id(4), &fn_traits));
+        assert!(!is_fn_trait(None, &[None, None, None]));
+    }
+
+    #[test]
+    fn test_boxed_closure() {
+        // A `Box<FnMut(u32) -> u32>` counting up, called twice through `call_mut`, and a
+        // `Fn(u32, u32) -> u32` called through `call_once`.
+        let vtable = vtable_object(&[("call_mut".to_string(), closure_entry("c"))]);
+        let call = virtual_call("call_mut", &["o".to_string(), "[2]".to_string()]);
+        let js = format!("function c(e,x){{e.get()[0]+=x;return e.get()[0]}}function d(e,x,y){{return e.get()[0]+x*y}}\
+                          var b=__cyano_box();b.set([0]);var o={{data:b,vtable:{}}};console.log({},{},{})",
+                         vtable, call, call, spread_call("d", &boxed_env("[1]"), "[2,3]", 2));
+        assert_eq!(runtime::eval(&js), "2 4 7\n");
+    }
+
+    #[test]
+    fn test_spread_call() {
+        // Closures are supported, so functions containing them don't fail with `UnsupportedInFn`.
+        // A closure capturing `a` and taking `(x, y)`, called through `Fn::call`.
+        let js = format!("function c(e,x,y){{return e[0]+x*y}}var a=1,t=[2,3];console.log({},{})",
+                         spread_call("c", "[a]", "t", 2), spread_call("function(e){return e.length}", "[]", "[]", 0));
+        assert_eq!(runtime::eval(&js), "7 0\n");
+    }
+
+    #[test]
+    fn test_copy_from_slice() {
+        // `dst.copy_from_slice(src)` as libcore implements it, copying the length of the raw
+        // source slice from its data pointer to the one of the destination.
+        let args = ["t0.f0".to_string(), "t1.f0".to_string(), "t0.f1".to_string()];
+        let js = format!("var s=[1,2,3],d=[0,0,0],a0={{get:function(){{return s}}}},a1={{get:function(){{return d}}}},\
+                          t0={},t1={};{}console.log(JSON.stringify([s,d]),t1.f1)",
+                         slice_repr("a0"), slice_repr("a1"), mem_intrinsic("copy_nonoverlapping", &args).unwrap());
+        assert_eq!(runtime::eval(&js), "[[1,2,3],[1,2,3]] 3\n");
+        assert!(mem_intrinsic("transmute", &args).is_none());
+    }
+
+    #[test]
+    fn test_unreachable_hook() {
+        // A test runner's hook sees the message of the trap, which then throws as usual.
+        let js = format!("var __cyano_panic_hook=function(m){{console.log(\"hook: \"+m)}};\
+                          try{{{}console.log(\"unreachable\")}}catch(e){{console.log(e.message)}}", UNREACHABLE);
+        assert_eq!(runtime::eval(&js), "hook: Basic block terminated with unreachable.\n\
+                                        Basic block terminated with unreachable.\n");
+    }
+
+    #[test]
+    fn test_slice_iter() {
+        // `for x in s.iter_mut() { *x *= 2; }`, with the `Option` of the next element in `t1`.
+        let js = format!("var a=[1,2,3],a0={{get:function(){{return a}}}},t0,t1;t0={};\
+                          while(true){{t1={};if(t1.d===0)break;t1.f0.set(t1.f0.get()*2)}}\
+                          console.log(JSON.stringify(a))",
+                         slice_iter("a0"), slice_iter_next("{get:function(){return t0}}"));
+        assert_eq!(runtime::eval(&js), "[2,4,6]\n");
+    }
+
+    #[test]
+    fn test_str_cmp() {
+        // "\u{ff61}" orders before "\u{1f600}" in Rust, but not by UTF-16 code units.
+        let cmp = |path: &str| str_cmp(&format!("core::cmp::{}", path), "a", "b").unwrap();
+        let js = format!("var a=\"\\uff61\",b=\"\\ud83d\\ude00\";\
+                          console.log({},{},{},JSON.stringify([{},{}]));b=a;console.log({},{},JSON.stringify({}))",
+                         cmp("PartialOrd::lt"), cmp("PartialOrd::ge"), cmp("PartialEq::eq"), cmp("Ord::cmp"),
+                         cmp("PartialOrd::partial_cmp"), cmp("PartialOrd::le"), cmp("PartialEq::ne"), cmp("Ord::cmp"));
+        assert_eq!(runtime::eval(&js), "true false false [{\"d\":-1},{\"d\":1,\"f0\":{\"d\":-1}}]\n\
+                                        true false {\"d\":0}\n");
+        assert!(str_cmp("core::cmp::Ord::max", "a", "b").is_none());
+    }
+
+    #[test]
+    fn test_volatile_memory() {
+        // Clearing a buffer, then copying another one into it, with the volatile intrinsics,
+        // which take the destination first.
+        let args = |args: &[&str]| args.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        let js = format!("var s=[1,2,3],d=[4,5,6],a0={{get:function(){{return s}}}},a1={{get:function(){{return d}}}};\
+                          {}console.log(JSON.stringify(d));{}console.log(JSON.stringify(d))",
+                         mem_intrinsic("volatile_set_memory", &args(&["a1", "0", "3"])).unwrap(),
+                         mem_intrinsic("volatile_copy_nonoverlapping_memory", &args(&["a1", "a0", "2"])).unwrap());
+        assert_eq!(runtime::eval(&js), "[0,0,0]\n[1,2,0]\n");
+    }
+
+    #[test]
+    fn test_split_at() {
+        // `let (a, b) = s.split_at_mut(1); b[0] = a[0] + 8;`, and splitting past the end.
+        let js = format!("var s=[1,2,3,4],a0={{get:function(){{return s}}}},t0;t0={};\
+                          t0[1].get()[0]=t0[0].get()[0]+8;\
+                          console.log(JSON.stringify(s),t0[0].get().length,t0[1].get().length);\
+                          try{{t0={}}}catch(e){{console.log(e.message)}}", split_at("a0", "1"), split_at("a0", "5"));
+        assert_eq!(runtime::eval(&js), "[1,9,3,4] 1 3\nassertion failed: mid <= len\n");
+    }
+
+    #[test]
+    fn test_clone_from_slice() {
+        // Cloning a `[(u32,); 2]` leaves the source untouched by writes to the destination, and a
+        // `[u32; 2]` is copied as is.
+        let js = format!("var s=[[1],[2]],d=[[0],[0]],a0={{get:function(){{return d}}}},\
+                          a1={{get:function(){{return s}}}};{}d[0][0]=9;\
+                          var x=[3,4],y=[0,0];{}console.log(JSON.stringify([s,d,y]))",
+                         clone_from_slice("a0", "a1", false),
+                         clone_from_slice("{get:function(){return y}}", "{get:function(){return x}}", true));
+        assert_eq!(runtime::eval(&js), "[[[1],[2]],[[9],[2]],[3,4]]\n");
+    }
+
+    #[test]
+    fn test_try_call() {
+        // `catch_unwind` of a closure returning, one panicking, and one aborting, which throws
+        // past it, as the browser's abort does.
+        let dest = Lvalue::Temp(Temp::new(0));
+        let call = |func| try_call(func, "a0", "a1", &dest);
+        assert_eq!(call("f"), "try{f(a0);t0=0;}catch(e){if(e===__cyano_abort)throw e;a1.set(e);t0=1;}");
+        let js = format!("{}var t0,a0=2,a1={{set:function(e){{console.log(\"payload: \"+e.message)}}}};\
+                          function f(x){{console.log(x)}}function g(x){{throw new Error(\"boom\")}}\
+                          function h(x){{__cyano_abort()}}{}console.log(t0);{}console.log(t0);\
+                          try{{{}}}catch(e){{console.log(e===__cyano_abort)}}",
+                         runtime::ABORT, call("f"), call("g"), call("h"));
+        assert_eq!(runtime::run(&js), "2\n0\npayload: boom\n1\ntrue\n");
+    }
+
+    #[test]
+    fn test_range_next() {
+        // `for i in a..b { n += i; }` (with the `Option` of the next value in `t1`) for `2..5` and
+        // the empty `3..3`.
+        let sum = format!("t0={{d:0,f0:a,f1:b}};n=0;while(true){{t1={};if(t1.d===0)break;n+=t1.f0}}\
+                           console.log(n,t0.f0);", range_next("{get:function(){return t0}}"));
+        let js = format!("var a=2,b=5,n,t0,t1;{0}a=b=3;{0}", sum);
+        assert_eq!(runtime::eval(&js), "9 5\n0 3\n");
+    }
+
+    #[test]
+    fn test_fn_mut_closure() {
+        // `s.binary_search_by(|x| { n += 1; x.cmp(&t) })` for a missing `t` of 4, with the
+        // closure counting its calls in its environment.
+        let js = format!("function c(e,x){{e.get()[0]++;return{{d:x.get()<e.get()[1]?-1:x.get()>e.get()[1]?1:0}}}}\
+                          var s=[1,3,3,5,8],e=[0,4];console.log(JSON.stringify(__cyano_binary_search(\
+                          {{get:function(){{return s}}}},{})),e[0])", fn_mut_closure("c", "e"));
+        assert_eq!(runtime::eval(&js), "{\"d\":1,\"f0\":3} 3\n");
+    }
+
+    #[test]
+    fn test_signum() {
+        // `x.signum()` and `1.0f64.copysign(x)` of zeros, NaN and negative numbers.
+        let args = ["1".to_string(), "x".to_string()];
+        let js = format!("console.log([0,-0,NaN,-3,Infinity].map(function(x){{return[{},{}]}}).join(\" \"))",
+                         signum("x"), float_intrinsic("copysignf64", &args).unwrap());
+        assert_eq!(runtime::eval(&js), "1,1 -1,-1 NaN,1 -1,-1 1,1\n");
+        assert!(float_intrinsic("sqrtf64", &args).is_none());
+    }
+
+    #[test]
+    fn test_rint() {
+        // Ties round to even, unlike with `Math.round`, and `-0.5` rounds to `-0.0`.
+        let js = format!("console.log([0.5,1.5,2.5,-2.5,2.4,-0.5].map(function(x){{return {}}}).join(),1/{})",
+                         float_intrinsic("rintf64", &["x".to_string()]).unwrap(),
+                         float_intrinsic("nearbyintf32", &["-0.5".to_string()]).unwrap());
+        assert_eq!(runtime::eval(&js), "0,2,2,-2,2,0 -Infinity\n");
+    }
+}
@@ -0,0 +1,137 @@
+//! Compiler configuration.
+
+/// The environment the output runs in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    /// A web page, where errors are shown in alerts, and the global object is `window`.
+    Browser,
+    /// Node.js, where errors go to the standard error, and the global object is `globalThis`.
+    ///
+    /// Aborts exit the process.
+    Node,
+    /// A Web Worker, where errors go to the console, and the global object is `self`.
+    ///
+    /// There is no DOM, so the output references neither `window` nor `alert`.
+    Worker,
+}
+
+impl Default for Target {
+    fn default() -> Target {
+        Target::Browser
+    }
+}
+
+/// The configuration of the compiler.
+///
+/// The default configuration is used for regular builds.
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    /// Emit a `debugger;` statement at the entry of every function.
+    ///
+    /// This makes breakpoints map to function entries when stepping through the generated code.
+    pub trace: bool,
+    /// Inline calls to trivial accessors.
+    ///
+    /// A trivial accessor is a function, which merely reads a field (or dereference) path of one
+    /// of its arguments, such as a getter. Calls to these are replaced by the read itself, and
+    /// accessors, which are only ever called, are not written at all.
+    pub inline: bool,
+    /// Emit the runtime checks of debug builds.
+    ///
+    /// This catches the errors, which native Rust panics on, but JavaScript silently carries on
+    /// after (e.g. division by zero giving `Infinity`).
+    pub checked: bool,
+    /// Strip every runtime check, giving the smallest and fastest output.
+    ///
+    /// This removes the bounds and overflow checks, which MIR always asserts, and overrides
+    /// `checked`. Like in C, an out of bounds index is then undefined behavior: reads give
+    /// `undefined`, and writes silently grow the array. `debug_assert!`s are controlled by
+    /// rustc's `debug_assertions` instead, as they're gone before MIR is built.
+    pub release: bool,
+    /// Follow every statement by a `//<file>:<line>` comment and a line break.
+    ///
+    /// Short of source maps, this correlates the (pretty-printed) output with the source. The
+    /// locations are only known when compiling with debug info, so the comments are left out
+    /// otherwise.
+    pub line_comments: bool,
+    /// Compute fused multiply-adds (e.g. `mul_add`) in software.
+    ///
+    /// By default, `mul_add` is a plain `a * b + c`, which rounds twice, so the result can differ
+    /// from the fused operation in the last bit. This mode rounds the result correctly, at the
+    /// cost of speed. Only `f64` operands beyond 1e150, or products overflowing or underflowing,
+    /// are still rounded twice.
+    pub precise_fma: bool,
+    /// The global object to export items under, instead of the global object itself.
+    ///
+    /// The object is created, unless it already exists, so exports of several programs can share
+    /// it. This keeps the exports from polluting the global scope.
+    pub namespace: Option<String>,
+    /// The name to export the entry function under, besides running it.
+    ///
+    /// The program still runs when loaded, but `main` can then be invoked again (e.g. `run()`),
+    /// which bridges demos and library use. It is exported like other items, so it goes under
+    /// `namespace` if one is set.
+    pub export_main: Option<String>,
+    /// Declare the locals of functions with `let` rather than `var`.
+    ///
+    /// Block scoped locals can help the optimizer of modern engines, but they aren't supported by
+    /// older ones. `const` isn't used, as the goto loop assigns most locals in several places.
+    pub let_declarations: bool,
+    /// Coerce numeric arguments and arithmetic to their types, in the style of asm.js.
+    ///
+    /// This is experimental. Integers of up to 32 bits are wrapped after every operation (e.g.
+    /// `(a+b)|0`), and floats are marked by `+(...)` or rounded by `Math.fround`, which engines
+    /// with asm.js fast paths can use to keep the values unboxed. The output isn't validated as
+    /// asm.js, and as a side effect, arithmetic wraps like in release builds.
+    pub asmjs: bool,
+    /// The environment the output runs in.
+    pub target: Target,
+    /// Run the program on every message posted to the Web Worker, rather than once when loaded.
+    ///
+    /// The posted data is held by `__cyano_message` while the program runs, so bindings can read
+    /// it with `js!("__cyano_message")`. This only applies to the `Worker` target.
+    pub worker_on_message: bool,
+    /// JavaScript inserted before the generated code, in the scope of the program.
+    ///
+    /// This is an escape hatch for defining extra helpers (e.g. an allocator for embedded JS to
+    /// use) or replacing the runtime. A runtime helper is left out when the prelude defines a
+    /// function of the same name, e.g. `function __cyano_trap(m){...}` replaces the trap every
+    /// panic goes through, and `function __cyano_abort(){...}` the abort.
+    pub prelude: Option<String>,
+}
+
+impl Config {
+    /// Are runtime checks of debug builds emitted?
+    pub fn checked(&self) -> bool {
+        self.checked && !self.release
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_default() {
+        // Regular builds neither trace nor inline, and run in the browser.
+        let config = Config::default();
+
+        assert!(!config.trace);
+        assert!(!config.inline);
+        assert!(config.namespace.is_none());
+        assert_eq!(config.target, Target::Browser);
+    }
+
+    #[test]
+    fn test_checked() {
+        let mut config = Config::default();
+        assert!(!config.checked());
+
+        config.checked = true;
+        assert!(config.checked());
+
+        // Release builds strip the checks regardless.
+        config.release = true;
+        assert!(!config.checked());
+    }
+}
@@ -0,0 +1,61 @@
+//! Emitting the generated code.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Write the JavaScript code `js` to the file at `path`.
+///
+/// The file is encoded in UTF-8 (so raw JavaScript containing non-ASCII characters is preserved
+/// as is), and it ends with a single `\n` like a `writeln!` would produce.
+pub fn write_js<P: AsRef<Path>>(path: P, js: &str) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(js.as_bytes())?;
+    if !js.ends_with('\n') {
+        file.write_all(b"\n")?;
+    }
+
+    file.flush()
+}
+
+/// Wrap the JavaScript code `js` in a minimal HTML page.
+///
+/// The program runs as soon as the page loads, so the page can be opened directly in a browser.
+/// Any `</script` in string literals is escaped, so it doesn't end the script element early.
+pub fn html(js: &str) -> String {
+    format!("<!DOCTYPE html>\n\
+             <html>\n\
+             <head><meta charset=\"utf-8\"></head>\n\
+             <body>\n\
+             <script>{}</script>\n\
+             </body>\n\
+             </html>\n", js.replace("</script", "<\\/script"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::env;
+    use std::fs::File;
+    use std::io::Read;
+
+    #[test]
+    fn test_write_js() {
+        let path = env::temp_dir().join("cyano_test_write_js.js");
+        write_js(&path, "(function(){alert(\"blå æøå ☃\")})();").unwrap();
+
+        let mut out = String::new();
+        File::open(&path).unwrap().read_to_string(&mut out).unwrap();
+        assert_eq!(out, "(function(){alert(\"blå æøå ☃\")})();\n");
+    }
+
+    #[test]
+    fn test_html() {
+        let page = html("(function(){d0_0();function d0_0(){var s=\"</script>\"}})();");
+
+        assert!(page.contains("<script>(function(){d0_0();"));
+        assert!(page.contains("<\\/script>"));
+        assert_eq!(page.matches("</script>").count(), 1);
+    }
+}
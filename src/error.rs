@@ -0,0 +1,188 @@
+//! Errors in the code generation.
+
+use rustc::mir::repr::{Rvalue, TerminatorKind};
+use rustc_const_math::{ConstMathErr, Op};
+use std::{error, fmt};
+use syntax::codemap::Span;
+
+/// An error, which stopped the compilation.
+#[derive(Debug)]
+pub enum CodegenError {
+    /// Writing the output failed.
+    Fmt(fmt::Error),
+    /// The MIR has a terminator, which can't be lowered yet.
+    UnsupportedTerminator {
+        /// The name of the terminator kind, e.g. `DropAndReplace`.
+        kind: &'static str,
+        /// The location of the terminator.
+        span: Span,
+    },
+    /// The MIR has an rvalue, which can't be lowered yet.
+    UnsupportedRvalue {
+        /// The name of the rvalue kind, e.g. `InlineAsm`.
+        kind: &'static str,
+        /// The location of the statement.
+        span: Span,
+    },
+    /// The source uses a feature, which can't be lowered yet.
+    Unsupported {
+        /// A description of the feature, e.g. `non-zero write_bytes of aggregates`.
+        feature: &'static str,
+        /// The location of the use.
+        span: Span,
+    },
+    /// A function uses a feature, which can't be lowered yet, somewhere in its body.
+    UnsupportedInFn {
+        /// A description of the feature, e.g. `promoted rvalues`.
+        feature: &'static str,
+        /// The path of the function, e.g. `app::main`.
+        item: String,
+        /// The location of the function.
+        span: Span,
+    },
+}
+
+impl From<fmt::Error> for CodegenError {
+    fn from(err: fmt::Error) -> CodegenError {
+        CodegenError::Fmt(err)
+    }
+}
+
+impl fmt::Display for CodegenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &CodegenError::Fmt(err) => write!(f, "failed to write the output: {}", err),
+            &CodegenError::UnsupportedTerminator { kind, span } =>
+                write!(f, "unsupported terminator: {} at {:?}", kind, span),
+            &CodegenError::UnsupportedRvalue { kind, span } =>
+                write!(f, "unsupported rvalue: {} at {:?}", kind, span),
+            &CodegenError::Unsupported { feature, span } =>
+                write!(f, "{} not yet supported at {:?}", feature, span),
+            &CodegenError::UnsupportedInFn { feature, ref item, span } =>
+                write!(f, "{} not yet supported in fn {} at {:?}", feature, item, span),
+        }
+    }
+}
+
+impl error::Error for CodegenError {
+    fn description(&self) -> &str {
+        match self {
+            &CodegenError::Fmt(_) => "failed to write the output",
+            &CodegenError::UnsupportedTerminator { .. } => "unsupported terminator",
+            &CodegenError::UnsupportedRvalue { .. } => "unsupported rvalue",
+            &CodegenError::Unsupported { feature, .. } => feature,
+            &CodegenError::UnsupportedInFn { feature, .. } => feature,
+        }
+    }
+}
+
+/// Get the name of the kind of a terminator.
+pub fn terminator_name(kind: &TerminatorKind) -> &'static str {
+    match kind {
+        &TerminatorKind::Goto { .. } => "Goto",
+        &TerminatorKind::If { .. } => "If",
+        &TerminatorKind::Switch { .. } => "Switch",
+        &TerminatorKind::SwitchInt { .. } => "SwitchInt",
+        &TerminatorKind::Resume => "Resume",
+        &TerminatorKind::Return => "Return",
+        &TerminatorKind::Unreachable => "Unreachable",
+        &TerminatorKind::Drop { .. } => "Drop",
+        &TerminatorKind::DropAndReplace { .. } => "DropAndReplace",
+        &TerminatorKind::Call { .. } => "Call",
+        &TerminatorKind::Assert { .. } => "Assert",
+    }
+}
+
+/// Get the name of the kind of an rvalue.
+pub fn rvalue_name(rvalue: &Rvalue) -> &'static str {
+    match rvalue {
+        &Rvalue::Use(..) => "Use",
+        &Rvalue::Repeat(..) => "Repeat",
+        &Rvalue::Ref(..) => "Ref",
+        &Rvalue::Len(..) => "Len",
+        &Rvalue::Cast(..) => "Cast",
+        &Rvalue::BinaryOp(..) => "BinaryOp",
+        &Rvalue::CheckedBinaryOp(..) => "CheckedBinaryOp",
+        &Rvalue::UnaryOp(..) => "UnaryOp",
+        &Rvalue::Box(..) => "Box",
+        &Rvalue::Aggregate(..) => "Aggregate",
+        &Rvalue::InlineAsm { .. } => "InlineAsm",
+    }
+}
+
+/// Get the panic message of a failed arithmetic assertion.
+///
+/// This is worded like the panics of debug builds of Rust (e.g. `attempt to add with overflow`),
+/// rather than like the const evaluator's errors.
+pub fn math_message(err: ConstMathErr) -> &'static str {
+    match err {
+        ConstMathErr::Overflow(Op::Add) => "attempt to add with overflow",
+        ConstMathErr::Overflow(Op::Sub) => "attempt to subtract with overflow",
+        ConstMathErr::Overflow(Op::Mul) => "attempt to multiply with overflow",
+        ConstMathErr::Overflow(Op::Div) => "attempt to divide with overflow",
+        ConstMathErr::Overflow(Op::Rem) => "attempt to calculate the remainder with overflow",
+        ConstMathErr::Overflow(Op::Neg) => "attempt to negate with overflow",
+        ConstMathErr::Overflow(Op::Shr) => "attempt to shift right with overflow",
+        ConstMathErr::Overflow(Op::Shl) => "attempt to shift left with overflow",
+        ConstMathErr::DivisionByZero => "attempt to divide by zero",
+        ConstMathErr::RemainderByZero => "attempt to calculate the remainder with a divisor of zero",
+        err => err.description(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rustc::mir::repr::{Lvalue, Rvalue, TerminatorKind};
+    use rustc_const_math::{ConstMathErr, Op};
+    use syntax::codemap::DUMMY_SP;
+
+    #[test]
+    fn test_unsupported_terminator() {
+        let err = CodegenError::UnsupportedTerminator {
+            kind: terminator_name(&TerminatorKind::Resume),
+            span: DUMMY_SP,
+        };
+
+        assert!(err.to_string().starts_with("unsupported terminator: Resume at "));
+    }
+
+    #[test]
+    fn test_unsupported_rvalue() {
+        let err = CodegenError::UnsupportedRvalue {
+            kind: rvalue_name(&Rvalue::Len(Lvalue::ReturnPointer)),
+            span: DUMMY_SP,
+        };
+
+        assert!(err.to_string().starts_with("unsupported rvalue: Len at "));
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let err = CodegenError::Unsupported {
+            feature: "write_bytes of enums",
+            span: DUMMY_SP,
+        };
+
+        assert!(err.to_string().starts_with("write_bytes of enums not yet supported at "));
+    }
+
+    #[test]
+    fn test_unsupported_in_fn() {
+        let err = CodegenError::UnsupportedInFn {
+            feature: "promoted rvalues",
+            item: "app::main".to_string(),
+            span: DUMMY_SP,
+        };
+
+        assert!(err.to_string().starts_with("promoted rvalues not yet supported in fn app::main at "));
+    }
+
+    #[test]
+    fn test_math_message() {
+        assert_eq!(math_message(ConstMathErr::Overflow(Op::Add)), "attempt to add with overflow");
+        assert_eq!(math_message(ConstMathErr::Overflow(Op::Shl)), "attempt to shift left with overflow");
+        assert_eq!(math_message(ConstMathErr::DivisionByZero), "attempt to divide by zero");
+    }
+}
@@ -0,0 +1,195 @@
+//! Hoisting of large constants.
+//!
+//! Large and repeated literals are declared once at the top of the program, and every use refers
+//! to the declaration by name, rather than repeating the literal. These are string literals and
+//! arrays of constant numbers (array expressions and repeats alike).
+
+use std::collections::HashMap;
+
+/// The minimal length of a literal (in JavaScript) for it to be hoisted.
+const MIN_LEN: usize = 32;
+/// The minimal length of a literal used more than once for it to be hoisted.
+///
+/// Shorter literals are about the size of the constant's name, so sharing them saves nothing.
+const MIN_REPEATED_LEN: usize = 8;
+
+/// The hoisted constants of a program.
+#[derive(Debug, Default)]
+pub struct ConstTable {
+    /// The literals, indexed by the number in the name of their constant.
+    consts: Vec<String>,
+    /// The number of uses of each literal in the program.
+    uses: HashMap<String, usize>,
+}
+
+impl ConstTable {
+    /// Count the uses of the literals `literals`.
+    ///
+    /// This is done for the whole program up front, so literals used more than once are hoisted
+    /// from their first use on.
+    pub fn count(&mut self, literals: &[String]) {
+        for lit in literals {
+            *self.uses.entry(lit.clone()).or_insert(0) += 1;
+        }
+    }
+
+    /// Replace the occurrences of the literals `literals` in the code `js` by their constants.
+    ///
+    /// The literals are given as JavaScript, and the constants are keyed by it, so equal literals
+    /// share a constant. Literals shorter than `MIN_LEN` are left as is, unless they are used more
+    /// than once and aren't shorter than `MIN_REPEATED_LEN`.
+    ///
+    /// Arrays are mutable, so every use of an array constant is a copy of it.
+    pub fn hoist(&mut self, js: &str, literals: &[String]) -> String {
+        let mut strs = HashMap::new();
+        let mut arrays = Vec::new();
+        for lit in literals {
+            let repeated = self.uses.get(lit).map_or(false, |&uses| uses > 1);
+            if lit.len() < MIN_LEN && !(repeated && lit.len() >= MIN_REPEATED_LEN) {
+                continue;
+            }
+
+            let id = match self.consts.iter().position(|x| x == lit) {
+                Some(id) => id,
+                None => {
+                    self.consts.push(lit.clone());
+                    self.consts.len() - 1
+                },
+            };
+            if lit.starts_with('"') {
+                strs.insert(&**lit, format!("c{:x}", id));
+            } else {
+                arrays.push((&**lit, format!("c{:x}.slice()", id)));
+            }
+        }
+
+        if strs.is_empty() && arrays.is_empty() {
+            return js.to_string();
+        }
+
+        // Replace whole string literals only, so a literal is never matched across the boundary
+        // of two other literals (e.g. `","` in `f("a","b")`).
+        let mut out = String::with_capacity(js.len());
+        let mut rest = js;
+        while let Some(start) = rest.find('"') {
+            out.push_str(&replace_arrays(&rest[..start], &arrays));
+            rest = &rest[start..];
+
+            // Find the closing quote, skipping escaped characters.
+            let mut end = rest.len();
+            let mut escaped = false;
+            for (n, c) in rest.char_indices().skip(1) {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => {
+                        end = n + 1;
+                        break;
+                    },
+                    _ => (),
+                }
+            }
+
+            let lit = &rest[..end];
+            out.push_str(strs.get(lit).map_or(lit, |x| &**x));
+            rest = &rest[end..];
+        }
+        out.push_str(&replace_arrays(rest, &arrays));
+
+        out
+    }
+
+    /// Get the declarations of the constants, if there are any.
+    pub fn declarations(&self) -> String {
+        if self.consts.is_empty() {
+            return String::new();
+        }
+
+        let consts: Vec<_> = self.consts.iter().enumerate()
+            .map(|(id, lit)| format!("c{:x}={}", id, lit))
+            .collect();
+        format!("var {};", consts.join(","))
+    }
+}
+
+/// Replace the arrays of `arrays` (pairs of the literals and their replacements) in the code `js`,
+/// which has no string literals.
+fn replace_arrays(js: &str, arrays: &[(&str, String)]) -> String {
+    let mut js = js.to_string();
+    for &(lit, ref name) in arrays {
+        js = js.replace(lit, name);
+    }
+
+    js
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_shared() {
+        let lit = "\"Lorem ipsum dolor sit amet, consectetur\"".to_string();
+        let mut table = ConstTable::default();
+
+        let a = table.hoist(&format!("function a(){{return {}}}", lit), &[lit.clone()]);
+        let b = table.hoist(&format!("function b(){{x={0};y={0}}}", lit), &[lit.clone(), lit.clone()]);
+        assert_eq!(a, "function a(){return c0}");
+        assert_eq!(b, "function b(){x=c0;y=c0}");
+        assert_eq!(table.declarations(), format!("var c0={};", lit));
+    }
+
+    #[test]
+    fn test_repeated() {
+        let lit = "\"index out of range\"".to_string();
+        let mut table = ConstTable::default();
+        table.count(&[lit.clone(), lit.clone()]);
+        table.count(&[lit.clone()]);
+
+        let a = table.hoist(&format!("function a(){{f({0},{0})}}", lit), &[lit.clone(), lit.clone()]);
+        let b = table.hoist(&format!("function b(){{f(\"x\",{})}}", lit), &[lit.clone()]);
+        assert_eq!(a, "function a(){f(c0,c0)}");
+        assert_eq!(b, "function b(){f(\"x\",c0)}");
+        assert_eq!(table.declarations(), format!("var c0={};", lit));
+    }
+
+    #[test]
+    fn test_boundary() {
+        let lit = "\",\"".to_string();
+        let mut table = ConstTable::default();
+        table.count(&[lit.clone(), lit.clone(), lit.clone()]);
+
+        // Too short to be hoisted, even though it's repeated.
+        assert_eq!(table.hoist("f(\"a\",\"b\",\",\")", &[lit.clone()]), "f(\"a\",\"b\",\",\")");
+
+        let lit = "\"\\\"quoted\\\", long\"".to_string();
+        table.count(&[lit.clone(), lit.clone()]);
+        assert_eq!(table.hoist(&format!("f(\"a\",{})", lit), &[lit.clone()]), "f(\"a\",c0)");
+    }
+
+    #[test]
+    fn test_short() {
+        let mut table = ConstTable::default();
+
+        assert_eq!(table.hoist("x=\"abc\"", &["\"abc\"".to_string()]), "x=\"abc\"");
+        assert_eq!(table.declarations(), "");
+    }
+
+    #[test]
+    fn test_arrays() {
+        let lit = format!("[{}]", (0..32).map(|x| format!("{},", x * x)).collect::<String>());
+        let repeat = "__cyano_repeat(0,4096)".to_string();
+        let mut table = ConstTable::default();
+        table.count(&[lit.clone(), lit.clone(), repeat.clone()]);
+        table.count(&[repeat.clone()]);
+
+        let a = table.hoist(&format!("function a(){{t0={0};t1={0};t2=\"{0}\"}}", lit), &[lit.clone(), lit.clone()]);
+        let b = table.hoist(&format!("function b(){{t0={};t1=\"[1,2,]\"}}", repeat), &[repeat.clone()]);
+        let c = table.hoist(&format!("function c(){{t0={}}}", repeat), &[repeat.clone()]);
+        // The array is emitted once, and copied on every use, but never in strings.
+        assert_eq!(a, format!("function a(){{t0=c0.slice();t1=c0.slice();t2=\"{}\"}}", lit));
+        assert_eq!(b, "function b(){t0=c1.slice();t1=\"[1,2,]\"}");
+        assert_eq!(c, "function c(){t0=c1.slice()}");
+        assert_eq!(table.declarations(), format!("var c0={},c1={};", lit, repeat));
+    }
+}
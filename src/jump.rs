@@ -0,0 +1,60 @@
+//! Jump tables for dense integer switches.
+
+/// The minimal number of cases for a jump table to be used.
+const MIN_CASES: usize = 8;
+
+/// Build a jump table for a switch over `cases`, which are pairs of values and target blocks.
+///
+/// The table is indexed by the switched value minus the returned offset, and gaps are filled
+/// with the `otherwise` block. Returns `None`, if the cases are too few or too sparse (less than
+/// half of the table filled) to be worth a table.
+pub fn jump_table(cases: &[(i64, usize)], otherwise: usize) -> Option<(i64, Vec<usize>)> {
+    if cases.len() < MIN_CASES {
+        return None;
+    }
+
+    let min = cases.iter().map(|&(val, _)| val).min().unwrap();
+    let max = cases.iter().map(|&(val, _)| val).max().unwrap();
+    let len = match max.checked_sub(min) {
+        Some(diff) if (diff as u64) < 2 * cases.len() as u64 => diff as usize + 1,
+        _ => return None,
+    };
+
+    let mut table = vec![otherwise; len];
+    for &(val, bb) in cases {
+        table[(val - min) as usize] = bb;
+    }
+
+    Some((min, table))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dense() {
+        let cases: Vec<_> = (0..256).map(|x| (x, x as usize + 1)).collect();
+        let (offset, table) = jump_table(&cases, 0).unwrap();
+
+        assert_eq!(offset, 0);
+        assert_eq!(table.len(), 256);
+        assert!(table.iter().enumerate().all(|(n, &bb)| bb == n + 1));
+    }
+
+    #[test]
+    fn test_gaps() {
+        let cases: Vec<_> = (0..10).map(|x| (x * 3 / 2 - 5, x as usize)).collect();
+        let (offset, table) = jump_table(&cases, 99).unwrap();
+
+        assert_eq!(offset, -5);
+        assert_eq!(table, [0, 1, 99, 2, 3, 99, 4, 5, 99, 6, 7, 99, 8, 9]);
+    }
+
+    #[test]
+    fn test_sparse() {
+        let cases: Vec<_> = (0..10).map(|x| (x * 100, x as usize)).collect();
+        assert!(jump_table(&cases, 0).is_none());
+        assert!(jump_table(&[(0, 1), (1, 2)], 0).is_none());
+    }
+}
@@ -1,8 +1,19 @@
 #![feature(question_mark, dotdot_in_tuple_patterns, box_patterns, rustc_private, str_escape)]
 
 extern crate rustc;
+extern crate rustc_const_math;
 extern crate rustc_data_structures;
+extern crate syntax;
 
+pub mod cache;
 pub mod codegen;
 pub mod compiler;
+pub mod config;
+pub mod emit;
+pub mod error;
+pub mod hoist;
+pub mod jump;
 pub mod cell;
+pub mod report;
+pub mod resolve;
+pub mod runtime;
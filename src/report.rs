@@ -0,0 +1,63 @@
+//! Reports on the generated code.
+
+use rustc::hir::def_id::DefId;
+use syntax::codemap::Span;
+
+/// The sizes of the generated code, in bytes.
+///
+/// This is useful for finding the functions dominating the output, when optimizing for size.
+#[derive(Clone, Debug, Default)]
+pub struct SizeReport {
+    /// The size of the code outside functions, i.e. the environment and the runtime.
+    pub overhead: usize,
+    /// The size of each function, in the order they were written.
+    pub fns: Vec<(DefId, usize)>,
+}
+
+impl SizeReport {
+    /// The total size of the generated code.
+    pub fn total(&self) -> usize {
+        self.fns.iter().fold(self.overhead, |acc, &(_, size)| acc + size)
+    }
+
+    /// Get the functions ordered by decreasing size.
+    pub fn largest(&self) -> Vec<(DefId, usize)> {
+        let mut fns = self.fns.clone();
+        fns.sort_by(|&(_, a), &(_, b)| b.cmp(&a));
+
+        fns
+    }
+}
+
+/// A construct, which is only lowered approximately, so its semantics may diverge from native
+/// Rust.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    /// The location of the construct.
+    pub span: Span,
+    /// How the lowering diverges.
+    pub message: &'static str,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rustc::hir::def_id::{DefId, DefIndex};
+
+    #[test]
+    fn test_report() {
+        let report = SizeReport {
+            overhead: 30,
+            fns: vec![
+                (DefId::local(DefIndex::new(0)), 100),
+                (DefId::local(DefIndex::new(1)), 400),
+                (DefId::local(DefIndex::new(2)), 20),
+            ],
+        };
+
+        assert_eq!(report.total(), 550);
+        assert_eq!(report.largest()[0], (DefId::local(DefIndex::new(1)), 400));
+        assert_eq!(report.largest()[2], (DefId::local(DefIndex::new(2)), 20));
+    }
+}
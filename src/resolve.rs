@@ -0,0 +1,56 @@
+//! Resolution of trait methods to their implementations.
+
+use rustc::hir::def_id::DefId;
+use rustc::traits::{self, Reveal};
+use rustc::ty::subst::Substs;
+use rustc::ty::{self, TyCtxt};
+
+/// Get the instance of the method implementing the trait method `method` with the type arguments
+/// `substs`.
+///
+/// The type arguments of the selected impl are resolved, so generic impls are instantiated like
+/// any other generic function. If the impl doesn't define the method, the trait's provided method
+/// is used. Returns `None` if no impl could be selected statically (e.g. for unresolved type
+/// parameters), or if the where clauses of the selected impl can't be fulfilled.
+pub fn impl_method<'a>(tcx: TyCtxt<'a, 'a, 'a>, method: DefId, substs: &'a Substs<'a>)
+                       -> Option<(DefId, &'a Substs<'a>)> {
+    let trait_id = match tcx.trait_of_item(method) {
+        Some(trait_id) => trait_id,
+        None => return None,
+    };
+    let trait_ref = ty::TraitRef::from_method(tcx, trait_id, substs);
+
+    let impl_data = tcx.normalizing_infer_ctxt(Reveal::All).enter(|infcx| {
+        let mut selcx = traits::SelectionContext::new(&infcx);
+        let obligation = traits::Obligation::new(traits::ObligationCause::dummy(),
+                                                 ty::Binder(trait_ref).to_poly_trait_predicate());
+
+        match selcx.select(&obligation) {
+            Ok(Some(traits::VtableImpl(data))) => {
+                // The impl's type arguments may be inferred from its where clauses. If these can't
+                // be fulfilled, the call is left generic.
+                let mut fulfill_cx = traits::FulfillmentContext::new();
+                for obligation in data.nested {
+                    fulfill_cx.register_predicate_obligation(&infcx, obligation);
+                }
+                infcx.drain_fulfillment_cx(&mut fulfill_cx, &data.substs).ok()
+                    .map(|impl_substs| (data.impl_def_id, impl_substs))
+            },
+            _ => None,
+        }
+    });
+
+    impl_data.map(|(impl_id, impl_substs)| {
+        let name = tcx.item_name(method);
+        let id = tcx.impl_or_trait_items(impl_id).iter()
+            .map(|item| item.def_id())
+            .find(|&id| tcx.item_name(id) == name);
+
+        match id {
+            // The method's own type arguments follow the impl's.
+            Some(id) => (id, substs.rebase_onto(tcx, trait_id, impl_substs)),
+            // Fall back to the provided method.
+            None => (method, substs),
+        }
+    })
+}
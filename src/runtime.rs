@@ -0,0 +1,603 @@
+//! The JavaScript runtime.
+//!
+//! These are helper functions, which are emitted into the anonymous environment before any
+//! compiled function, such that the generated code can refer to them by name.
+
+use config::Target;
+
+/// Abort execution with the error message `m`.
+///
+/// Every panic and runtime check goes through this function. By default, the message is shown in
+/// an alert, but when a global `__cyano_panic_hook` function is defined (e.g. by a test runner,
+/// before loading the program), the message is handed to it instead. Either way, an exception is
+/// thrown afterwards, so execution never continues past the trap.
+pub const TRAP: &'static str = "function __cyano_trap(m){\
+                                    if(typeof __cyano_panic_hook===\"function\")__cyano_panic_hook(m);\
+                                    else alert(\"Cyano error: \"+m);\
+                                    throw new Error(m)\
+                                }";
+
+/// Abort execution with the error message `m`, when running on Node.js or in a Web Worker.
+///
+/// This is `TRAP`, except that the message goes to the console (the standard error on Node.js),
+/// as there are no alerts.
+pub const NODE_TRAP: &'static str = "function __cyano_trap(m){\
+                                         if(typeof __cyano_panic_hook===\"function\")__cyano_panic_hook(m);\
+                                         else console.error(\"Cyano error: \"+m);\
+                                         throw new Error(m)\
+                                     }";
+
+/// Get the address of the pointer `p`, as given by casting it to an integer.
+///
+/// There are no addresses in JavaScript, so a unique id is allocated for every pointee on its
+/// first cast. Pointees, which are values rather than objects, have no identity, so the pointer
+/// object itself is used instead. Hence, equal ids are only guaranteed for casts of the same
+/// pointer value (and its copies): two references to the same integer, each taken by its own
+/// borrow, have different addresses. The ids are nonzero and aligned, so they're never mistaken
+/// for null.
+pub const ADDR: &'static str = "function __cyano_addr(p){\
+                                    var m=__cyano_addr.m||(__cyano_addr.m=new WeakMap()),k=p.get();\
+                                    if(k===null||typeof k!==\"object\"&&typeof k!==\"function\")k=p;\
+                                    if(!m.has(k))m.set(k,__cyano_addr.n=(__cyano_addr.n||0)+8);\
+                                    return m.get(k)\
+                                }";
+
+/// Abort execution immediately.
+///
+/// Unlike a panic, an abort doesn't unwind, so it must not be caught by `catch_unwind`. The
+/// function itself is thrown, as a sentinel, which `try` rethrows. A global
+/// `__cyano_abort_hook` function is called first, if defined.
+pub const ABORT: &'static str = "function __cyano_abort(){\
+                                     if(typeof __cyano_abort_hook===\"function\")__cyano_abort_hook();\
+                                     throw __cyano_abort\
+                                 }";
+
+/// Abort execution immediately, when running on Node.js.
+///
+/// Unlike `ABORT`, this exits the process, with the status of a native abort.
+pub const NODE_ABORT: &'static str = "function __cyano_abort(){\
+                                          if(typeof __cyano_abort_hook===\"function\")__cyano_abort_hook();\
+                                          process.exit(134)\
+                                      }";
+
+/// Copy `n` elements from the array behind pointer `s` to the array behind pointer `d`.
+///
+/// Pointers are represented by the reference object of the array they point into (`as_ptr` is
+/// merely a cast), hence the backing arrays are obtained through `get()`. These may be views over
+/// the same array (see `SLICE_VIEW`), which overlap, so the elements are read into a temporary
+/// array before any is written, like `memmove` does.
+pub const COPY: &'static str = "function __cyano_copy(s,d,n){\
+                                    s=s.get();d=d.get();\
+                                    for(var t=[],i=0;i<n;i++)t.push(s[i]);\
+                                    for(i=0;i<n;i++)d[i]=t[i]\
+                                }";
+
+/// Set `n` elements of the array behind pointer `d` to `v`.
+///
+/// This is the equivalent of `memset` for byte buffers.
+pub const SET: &'static str = "function __cyano_set(d,v,n){d=d.get();for(var i=0;i<n;i++)d[i]=v}";
+
+/// Make a copy of a value, which doesn't share any aggregates with the original.
+///
+/// Arrays and objects are copied recursively, except references (anything with a `get` method),
+/// which still point to the same value after the copy, like they do in Rust.
+pub const DUP: &'static str = "function __cyano_dup(x){\
+                                   if(typeof x!==\"object\"||x===null||typeof x.get===\"function\")return x;\
+                                   if(Array.isArray(x))return x.map(__cyano_dup);\
+                                   var y={};for(var k in x)y[k]=__cyano_dup(x[k]);return y\
+                               }";
+
+/// Clone the elements of the slice `s` into the slice `d` of the same length.
+///
+/// If `c` is true, the elements are scalars, which are copied as is (through `__cyano_copy`).
+/// Otherwise, each element is deep copied, as plain assignment would alias the aggregates.
+pub const CLONE_FROM_SLICE: &'static str = "function __cyano_clone_from_slice(d,s,c){\
+                                                if(d.get().length!==s.get().length)\
+                                                    __cyano_trap(\"destination and source slices have different lengths\");\
+                                                if(c)__cyano_copy(s,d,s.get().length);\
+                                                else{d=d.get();s=s.get();for(var i=0;i<s.length;i++)d[i]=__cyano_dup(s[i])}\
+                                            }";
+
+/// Create an array of `n` copies of `x`.
+///
+/// Unlike `fill`, every slot gets its own copy, so mutating one element of an array of
+/// aggregates leaves the rest untouched.
+pub const REPEAT: &'static str = "function __cyano_repeat(x,n){\
+                                      for(var a=[],i=0;i<n;i++)a.push(__cyano_dup(x));return a\
+                                  }";
+
+/// Add `x` and `y`, rounding to odd.
+///
+/// Rather than to nearest, an inexact sum is rounded to the neighbor with an odd last bit. This
+/// keeps the sticky information of the discarded bits, so a later rounding to nearest (of a sum
+/// or to a narrower type) rounds as if the exact sum was used, i.e. it doesn't double round.
+pub const ADD_ODD: &'static str = "function __cyano_add_odd(x,y){\
+                                       var s=x+y,z=s-x,e=x-(s-z)+(y-z);\
+                                       if(e===0||!isFinite(s))return s;\
+                                       var v=new DataView(new ArrayBuffer(8));v.setFloat64(0,s);\
+                                       var l=v.getUint32(4);\
+                                       if(l&1)return s;\
+                                       if(e>0===s>0)v.setUint32(4,l+1);\
+                                       else if(l)v.setUint32(4,l-1);\
+                                       else{v.setUint32(0,v.getUint32(0)-1);v.setUint32(4,4294967295)}\
+                                       return v.getFloat64(0)\
+                                   }";
+
+/// Compute `a*b+c`, correctly rounded.
+///
+/// The product is split into its rounded value and the exact error (by Dekker's algorithm), and
+/// the addend is added to the rounded product with its error (by Knuth's two-sum). The two errors
+/// are summed with rounding to odd, so the final addition is the only rounding to nearest
+/// (following Boldo and Melquiond). This assumes that neither the split nor the errors over- or
+/// underflow, so infinite or zero products, and operands too large to split, fall back to the
+/// unfused expression.
+pub const FMA: &'static str = "function __cyano_fma(a,b,c){\
+                                   var p=a*b;\
+                                   if(!isFinite(p)||p===0||!isFinite(c)||Math.abs(a)>1e150||Math.abs(b)>1e150)return p+c;\
+                                   var s=134217729,t=s*a,ah=t-(t-a),al=a-ah;t=s*b;\
+                                   var bh=t-(t-b),bl=b-bh,e=ah*bh-p+ah*bl+al*bh+al*bl,h=c+p,z=h-c,l=c-(h-z)+(p-z);\
+                                   return h+__cyano_add_odd(l,e)\
+                               }";
+
+/// Reverse the order of the `n` low bytes of the integer `v`.
+///
+/// Negative values are taken in two's complement, and the result is unsigned, so signed results
+/// must be wrapped afterwards.
+pub const BSWAP: &'static str = "function __cyano_bswap(v,n){\
+                                     for(var r=0,i=0;i<n;i++){var b=v%256;if(b<0)b+=256;r=r*256+b;v=(v-b)/256}\
+                                     return r\
+                                 }";
+
+/// Give `x` the sign of `y`.
+///
+/// The sign bit of `y` is read from its representation, as comparisons can't tell `-0.0` from
+/// `0.0` (and `Math.sign` keeps the zero as is).
+pub const COPYSIGN: &'static str = "function __cyano_copysign(x,y){\
+                                        var v=new DataView(new ArrayBuffer(8));\
+                                        v.setFloat64(0,y);\
+                                        return v.getUint8(0)>>7?-Math.abs(x):Math.abs(x)\
+                                    }";
+
+/// Round `x` to the nearest integer, with ties to even.
+///
+/// `Math.round` rounds ties up, so ties, which it rounded to an odd integer, are corrected down.
+/// The sign of zero is kept, e.g. `-0.5` rounds to `-0.0`.
+pub const RINT: &'static str = "function __cyano_rint(x){\
+                                    var r=Math.round(x);\
+                                    return Math.abs(x%1)===0.5&&r%2!==0?r-1:r\
+                                }";
+
+/// Compare the strings `a` and `b`, returning -1, 0 or 1.
+///
+/// The strings are ordered by code points, which is equivalent to Rust's ordering of the UTF-8
+/// bytes. Comparing UTF-16 code units (as JS does) only differs in that surrogates must order
+/// after the units from `0xE000`, so these are remapped on mismatch.
+pub const CMP_STR: &'static str = "function __cyano_cmp_str(a,b){\
+                                       for(var i=0;i<a.length&&i<b.length;i++){\
+                                           var x=a.charCodeAt(i),y=b.charCodeAt(i);\
+                                           if(x!==y){\
+                                               if(x>=55296&&y>=55296){\
+                                                   x=x>=57344?x-2048:x+8192;y=y>=57344?y-2048:y+8192\
+                                               }\
+                                               return x<y?-1:1\
+                                           }\
+                                       }\
+                                       return a.length<b.length?-1:a.length>b.length?1:0\
+                                   }";
+
+/// Allocate an empty box.
+///
+/// Boxes have the same interface as references, so dereferencing a box is a `get()` or `set()`
+/// call regardless of where it came from. The value is held by the closures rather than by the
+/// object, so the accessors don't depend on the receiver they are called on, and the box stays
+/// intact when returned from or passed to other functions.
+pub const BOX: &'static str = "function __cyano_box(){var x;return{get:function(){return x},set:function(y){x=y}}}";
+
+/// Advance the range `r` (`ops::Range`), returning an `Option` of the next value.
+///
+/// This is the whole iterator for `for i in a..b` loops, so they boil down to a counting loop
+/// over the range's fields, rather than going through libcore's generic `Step` machinery.
+pub const RANGE_NEXT: &'static str = "function __cyano_range_next(r){return r.f0<r.f1?{d:1,f0:r.f0++}:{d:0}}";
+
+/// Create an iterator over the slice behind reference `s`.
+///
+/// This replaces libcore's pointer-based `slice::Iter` (and `IterMut`) by an index into the
+/// backing array.
+pub const SLICE_ITER: &'static str = "function __cyano_slice_iter(s){return{a:s.get(),i:0}}";
+
+/// Advance the slice iterator `it`, returning an `Option` of a reference to the element.
+pub const SLICE_ITER_NEXT: &'static str = "function __cyano_slice_iter_next(it){\
+                                               if(it.i>=it.a.length)return{d:0};\
+                                               var a=it.a,i=it.i++;\
+                                               return{d:1,f0:{get:function(){return a[i]},set:function(x){a[i]=x}}}\
+                                           }";
+
+/// Get an `Option` of a reference to the element at index `i` of the slice `s`.
+///
+/// This is `None` if `i` is out of bounds (including negative, as `last` of an empty slice
+/// gives -1).
+pub const SLICE_GET: &'static str = "function __cyano_slice_get(s,i){\
+                                         var a=s.get();\
+                                         if(i<0||i>=a.length)return{d:0};\
+                                         return{d:1,f0:{get:function(){return a[i]},set:function(x){a[i]=x}}}\
+                                     }";
+
+/// Create a reference to the subslice of `n` elements starting at index `o` of the slice `s`.
+///
+/// The slice is a view over the same backing array, such that writes through it are visible in
+/// the original. It proxies the indices and `length` to the range, so indexing and `Len` (and
+/// the array methods) work on it like on any other array.
+pub const SLICE_VIEW: &'static str = "function __cyano_slice_view(s,o,n){\
+                                          var a=s.get(),i=function(k){return typeof k===\"string\"&&/^\\d+$/.test(k)?+k:-1};\
+                                          var v=new Proxy(a,{\
+                                              get:function(t,k,r){var j=i(k);return j>=0?a[o+j]:k===\"length\"?n:Reflect.get(t,k,r)},\
+                                              set:function(t,k,x,r){var j=i(k);return j>=0?(a[o+j]=x,true):Reflect.set(t,k,x,r)}\
+                                          });\
+                                          return{get:function(){return v}}\
+                                      }";
+
+/// Split the slice `s` at index `m`, returning a tuple of the two non-overlapping views.
+pub const SPLIT_AT: &'static str = "function __cyano_split_at(s,m){\
+                                        var n=s.get().length;\
+                                        if(m>n)__cyano_trap(\"assertion failed: mid <= len\");\
+                                        return[__cyano_slice_view(s,0,m),__cyano_slice_view(s,m,n-m)]\
+                                    }";
+
+/// Index the slice `s` by the range from `a` to `b` (or the end, if `b` is `undefined`),
+/// returning a view of the subslice.
+///
+/// The view carries its own length, so it can be passed around like any other slice.
+pub const SLICE_INDEX: &'static str = "function __cyano_slice_index(s,a,b){\
+                                           var n=s.get().length;\
+                                           if(b===undefined)b=n;\
+                                           if(a>b)__cyano_trap(\"slice index starts at \"+a+\" but ends at \"+b);\
+                                           if(b>n)__cyano_trap(\"index \"+b+\" out of range for slice of length \"+n);\
+                                           return __cyano_slice_view(s,a,b-a)\
+                                       }";
+
+/// Binary search the slice `s` with the comparison function `f`, which takes a reference to an
+/// element and returns an `Ordering`.
+///
+/// This follows libcore's algorithm, so equal elements give the same index as in native Rust.
+/// The result is a `Result` of the index.
+pub const BINARY_SEARCH: &'static str = "function __cyano_binary_search(s,f){\
+                                             var a=s.get(),b=0,n=a.length;\
+                                             while(true){\
+                                                 var h=n>>1;\
+                                                 if(n===h)return{d:1,f0:b};\
+                                                 var c=f(function(i){return{get:function(){return a[i]}}}(b+h)).d;\
+                                                 if(c<0){b+=h+1;n-=h+1}else if(c>0)n=h;else return{d:0,f0:b+h}\
+                                             }\
+                                         }";
+
+/// All the runtime helpers, in the order they're emitted.
+pub const HELPERS: &'static [&'static str] = &[TRAP, ADDR, ABORT, COPY, SET, DUP, CLONE_FROM_SLICE, REPEAT, BOX,
+                                                    ADD_ODD, FMA, BSWAP, COPYSIGN, RINT, CMP_STR, RANGE_NEXT, SLICE_ITER,
+                                                    SLICE_ITER_NEXT, SLICE_GET, SLICE_VIEW, SPLIT_AT, SLICE_INDEX, BINARY_SEARCH];
+
+/// Get the runtime helpers for `target`, in the order they're emitted.
+pub fn helpers(target: Target) -> Vec<&'static str> {
+    HELPERS.iter().map(|&helper| match target {
+        Target::Browser => helper,
+        Target::Node if helper == TRAP => NODE_TRAP,
+        Target::Node if helper == ABORT => NODE_ABORT,
+        Target::Node => helper,
+        // Workers can't exit the process, so aborts throw like in the browser.
+        Target::Worker if helper == TRAP => NODE_TRAP,
+        Target::Worker => helper,
+    }).collect()
+}
+
+/// Check if the JavaScript `js` declares the function of the runtime helper `helper`.
+///
+/// The declaration must be written like the helpers are, i.e. `function __cyano_trap(`.
+pub fn declares(js: &str, helper: &str) -> bool {
+    match helper.find('(') {
+        Some(end) => js.contains(&helper[..end + 1]),
+        None => false,
+    }
+}
+
+/// Run the JavaScript `js` on Node.js, and get its standard output.
+///
+/// Panics if Node.js isn't installed, so the tests fail rather than pass without running, and
+/// with the standard error if `js` throws.
+#[cfg(test)]
+pub fn run(js: &str) -> String {
+    use std::process::Command;
+
+    let output = Command::new("node").arg("-e").arg(js).output().expect("Node.js is required to run the tests.");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Run the JavaScript `js` on Node.js after the runtime helpers, and get its standard output.
+#[cfg(test)]
+pub fn eval(js: &str) -> String {
+    run(&format!("{}{}", helpers(Target::Node).join(""), js))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use config::Target;
+
+    #[test]
+    fn test_node_helpers() {
+        let node = helpers(Target::Node);
+
+        assert_eq!(node.len(), HELPERS.len());
+        assert!(node.iter().all(|helper| !helper.contains("alert") && !helper.contains("window")));
+        assert!(helpers(Target::Browser).iter().any(|helper| helper.contains("alert")));
+    }
+
+    /// Get the embedded JavaScript of the function `name` in the libcyano source `src`.
+    ///
+    /// The methods of libcyano are written against the same value model as the runtime, so they
+    /// are tested here.
+    fn embedded_js(src: &str, name: &str) -> String {
+        let body = &src[src.find(&format!("fn {}", name)).unwrap()..];
+        let js = &body[body.find("js!(\"").unwrap() + 5..];
+        // Lines continued with a backslash go on after the leading whitespace of the next one.
+        let lines: Vec<_> = js[..js.find("\")").unwrap()].split("\\\n").map(|x: &str| x.trim_left()).collect();
+        lines.concat().replace("\\\"", "\"")
+    }
+
+    #[test]
+    fn test_option_take() {
+        let src = include_str!("../libcyano/core.rs");
+        // Options are `Some` (0) or `None` (1), and are taken by reference.
+        let js = format!("function take(a0){{{}}}function replace(a0,a1){{{}}}\
+                          var x={{d:0,f0:2}},p={{get:function(){{return x}},set:function(y){{x=y}}}};\
+                          console.log(JSON.stringify([take(p),x,take(p),replace(p,3),replace(p,4),x]))",
+                         embedded_js(src, "take"), embedded_js(src, "replace"));
+        assert_eq!(eval(&js), "[{\"d\":0,\"f0\":2},{\"d\":1},{\"d\":1},{\"d\":1},{\"d\":0,\"f0\":3},\
+                               {\"d\":0,\"f0\":4}]\n");
+    }
+
+    #[test]
+    fn test_sort_by() {
+        let src = include_str!("../libcyano/src/vec.rs");
+        // Sort pairs descending by their first element, which must keep equal pairs in order. The
+        // comparison function gets the closure environment and references to the elements.
+        let js = format!("function sort(a0,a1,a2){{{}}}\
+                          var v=[[2,0],[3,1],[1,2],[3,3],[2,4]],e={{}};\
+                          sort({{get:function(){{return v}}}},e,function(c,x,y){{\
+                              if(c!==e)throw new Error(\"environment\");\
+                              var a=x.get()[0],b=y.get()[0];return{{d:a<b?1:a>b?-1:0}}\
+                          }});\
+                          console.log(JSON.stringify(v))", embedded_js(src, "sort<T"));
+        assert_eq!(eval(&js), "[[3,1],[3,3],[2,0],[2,4],[1,2]]\n");
+    }
+
+    #[test]
+    fn test_slice_index() {
+        let js = "var a=[1,2,3,4,5],s={get:function(){return a}},v=__cyano_slice_index(s,1,4),w=__cyano_slice_index(v,1);\
+                  v.get()[2]=9;\
+                  console.log(v.get().length,v.get()[0],a[3],w.get().length,w.get()[0],__cyano_slice_index(s,5).get().length);\
+                  try{__cyano_slice_index(s,3,2)}catch(e){console.log(e.message)}\
+                  try{__cyano_slice_index(v,0,4)}catch(e){console.log(e.message)}";
+        assert_eq!(eval(js), "3 2 9 2 3 0\nslice index starts at 3 but ends at 2\n\
+                              index 4 out of range for slice of length 3\n");
+    }
+
+    #[test]
+    fn test_addr() {
+        let js = "var o={d:0},x=1,p={get:function(){return o}},q={get:function(){return o}},\
+                  r={get:function(){return x}},s={get:function(){return x}},\
+                  a=[__cyano_addr(p),__cyano_addr(q),__cyano_addr(r),__cyano_addr(r),__cyano_addr(s)];\
+                  console.log(a[0]===a[1],a[2]===a[3],a[2]!==a[4],a.every(function(x){return x>0&&x%8===0}))";
+        assert_eq!(eval(js), "true true true true\n");
+    }
+
+    #[test]
+    fn test_declares() {
+        let prelude = "function __cyano_trap(m){console.log(m);throw m}";
+
+        assert!(declares(prelude, TRAP));
+        assert!(declares(prelude, NODE_TRAP));
+        assert!(!declares(prelude, ABORT));
+        assert!(!declares("function __cyano_trap_hook(m){}", TRAP));
+    }
+
+    #[test]
+    fn test_worker_helpers() {
+        let worker = helpers(Target::Worker);
+
+        assert_eq!(worker.len(), HELPERS.len());
+        assert!(worker.iter().all(|helper| {
+            !helper.contains("alert") && !helper.contains("window") && !helper.contains("document")
+                && !helper.contains("process")
+        }));
+    }
+
+    #[test]
+    fn test_copy_overlapping() {
+        // Copying to a view one element further into the same array, as `ptr::copy` does when
+        // inserting into a slice.
+        let js = "var a=[1,2,3,4,5],s={get:function(){return a}};\
+                  __cyano_copy(__cyano_slice_view(s,0,4),__cyano_slice_view(s,1,4),4);console.log(JSON.stringify(a));\
+                  __cyano_copy(__cyano_slice_view(s,2,3),__cyano_slice_view(s,0,3),3);\
+                  console.log(JSON.stringify(a))";
+        assert_eq!(eval(js), "[1,1,2,3,4]\n[2,3,4,3,4]\n");
+    }
+
+    #[test]
+    fn test_copy() {
+        let js = "var a=[1,2,3],b=[0,0,0,0],s={get:function(){return a}},d={get:function(){return b}};\
+                  __cyano_copy(s,d,2);__cyano_copy(s,s,3);\
+                  console.log(JSON.stringify([a,b]))";
+        assert_eq!(eval(js), "[[1,2,3],[1,2,0,0]]\n");
+    }
+
+    #[test]
+    fn test_dup() {
+        // References are shared by the copies, aggregates aren't.
+        let js = "var x=1,p={get:function(){return x}},a={d:0,f0:[1,2],f1:p},b=__cyano_dup(a),r=__cyano_repeat([0],2);\
+                  b.f0[0]=3;r[0][0]=4;\
+                  console.log(JSON.stringify([a.f0,b.f0,r]),b.f1===p)";
+        assert_eq!(eval(js), "[[1,2],[3,2],[[4],[0]]] true\n");
+    }
+
+    #[test]
+    fn test_panic_hook() {
+        let js = "var __cyano_panic_hook=function(m){console.log(\"hook: \"+m)};\
+                  try{__cyano_trap(\"oops\");console.log(\"unreachable\")}catch(e){console.log(e.message)}";
+        assert_eq!(eval(js), "hook: oops\noops\n");
+    }
+
+    #[test]
+    fn test_slice_iter() {
+        let js = "var a=[1,2],it=__cyano_slice_iter({get:function(){return a}}),x=__cyano_slice_iter_next(it);\
+                  x.f0.set(x.f0.get()+9);\
+                  console.log(JSON.stringify([a,__cyano_slice_iter_next(it).f0.get(),__cyano_slice_iter_next(it)]))";
+        assert_eq!(eval(js), "[[10,2],2,{\"d\":0}]\n");
+    }
+
+    #[test]
+    fn test_cmp_str() {
+        // U+FFFF orders before U+10000, although its UTF-16 code unit doesn't.
+        let js = "console.log(__cyano_cmp_str(\"a\",\"ab\"),__cyano_cmp_str(\"b\",\"ab\"),__cyano_cmp_str(\"ab\",\"ab\"),\
+                  __cyano_cmp_str(\"\\uFFFF\",\"\\uD800\\uDC00\"),__cyano_cmp_str(\"\\uD800\\uDC00\",\"\\uE000\"))";
+        assert_eq!(eval(js), "-1 1 0 -1 1\n");
+    }
+
+    #[test]
+    fn test_set() {
+        let js = "var a=[1,2,3];__cyano_set({get:function(){return a}},7,2);console.log(JSON.stringify(a))";
+        assert_eq!(eval(js), "[7,7,3]\n");
+    }
+
+    #[test]
+    fn test_box() {
+        // The accessors keep working when detached from the box.
+        let js = "var b=__cyano_box(),c=__cyano_box(),get=b.get,set=b.set;\
+                  set(1);c.set(2);console.log(get(),b.get(),c.get())";
+        assert_eq!(eval(js), "1 1 2\n");
+    }
+
+    #[test]
+    fn test_split_at() {
+        let js = "var a=[1,2,3],s={get:function(){return a}},p=__cyano_split_at(s,1),l=p[0].get(),r=p[1].get();\
+                  r[1]=9;\
+                  console.log(l.length,r.length,r[0],a[2],JSON.stringify(r.slice()));\
+                  try{__cyano_split_at(s,4)}catch(e){console.log(e.message)}";
+        assert_eq!(eval(js), "1 2 2 9 [2,9]\nassertion failed: mid <= len\n");
+    }
+
+    #[test]
+    fn test_fma() {
+        // The product of 1+2^-30 and 1-2^-30 is 1-2^-60, which is lost by rounding the product. Also,
+        // (1+2^-52)^2-8 is slightly above a halfway case, which rounding the error terms to nearest
+        // turns into a tie.
+        let js = "var e=Math.pow(2,-30),f=1+Math.pow(2,-52);\
+                  console.log(__cyano_fma(1+e,1-e,-1)===-Math.pow(2,-60),(1+e)*(1-e)-1,__cyano_fma(f,f,-8),f*f-8,\
+                  __cyano_fma(2,3,4),__cyano_fma(1e200,1e200,1))";
+        assert_eq!(eval(js), "true 0 -6.999999999999999 -7 10 Infinity\n");
+    }
+
+    #[test]
+    fn test_add_odd() {
+        // (1+2^-12)^2 is halfway between two `f32`s, so the tiny addend decides the rounding.
+        let js = "var f=1+Math.pow(2,-12),t=Math.pow(2,-70);\
+                  console.log(Math.fround(__cyano_add_odd(f*f,t)),Math.fround(f*f+t),__cyano_add_odd(1,2),\
+                  __cyano_add_odd(1,Math.pow(2,-60))-1===Math.pow(2,-52))";
+        assert_eq!(eval(js), "1.0004884004592896 1.00048828125 3 true\n");
+    }
+
+    #[test]
+    fn test_clone_from_slice() {
+        let js = "var a=[[1]],b=[[0]],c=[1,2],d=[0,0],r=function(x){return{get:function(){return x}}};\
+                  __cyano_clone_from_slice(r(b),r(a),false);__cyano_clone_from_slice(r(d),r(c),true);a[0][0]=5;\
+                  console.log(JSON.stringify([b,d]));\
+                  try{__cyano_clone_from_slice(r(d),r(a),true)}catch(e){console.log(e.message)}";
+        assert_eq!(eval(js), "[[[1]],[1,2]]\ndestination and source slices have different lengths\n");
+    }
+
+    #[test]
+    fn test_abort() {
+        // The browser abort throws itself, after calling the hook.
+        let js = format!("{}var __cyano_abort_hook=function(){{console.log(\"hook\")}};\
+                          try{{__cyano_abort();console.log(\"unreachable\")}}catch(e){{console.log(e===__cyano_abort)}}",
+                         ABORT);
+        assert_eq!(eval(&js), "hook\ntrue\n");
+    }
+
+    #[test]
+    fn test_range_next() {
+        let js = "var r={f0:1,f1:3};\
+                  console.log(JSON.stringify([__cyano_range_next(r),__cyano_range_next(r),__cyano_range_next(r),r]))";
+        assert_eq!(eval(js), "[{\"d\":1,\"f0\":1},{\"d\":1,\"f0\":2},{\"d\":0},{\"f0\":3,\"f1\":3}]\n");
+    }
+
+    #[test]
+    fn test_binary_search() {
+        // `Ok` is 0 and `Err` is 1, and the orderings are -1, 0 and 1.
+        let js = "var a=[1,2,2,2,5],s={get:function(){return a}},f=function(x){return function(r){\
+                  return{d:r.get()<x?-1:r.get()>x?1:0}}};\
+                  console.log(JSON.stringify([2,3,0,6].map(function(x){return __cyano_binary_search(s,f(x))})))";
+        assert_eq!(eval(js), "[{\"d\":0,\"f0\":2},{\"d\":1,\"f0\":4},{\"d\":1,\"f0\":0},{\"d\":1,\"f0\":5}]\n");
+    }
+
+    #[test]
+    fn test_copysign() {
+        let js = "console.log([__cyano_copysign(2,-0),__cyano_copysign(-2,0),__cyano_copysign(0,-1),\
+                  __cyano_copysign(-3,NaN)].map(function(x){return Object.is(x,-0)?\"-0\":x}).join(\" \"))";
+        assert_eq!(eval(js), "-2 2 -0 3\n");
+    }
+
+    #[test]
+    fn test_node_abort() {
+        // Only Node.js can exit the process, so workers throw like the browser.
+        assert!(helpers(Target::Node).contains(&NODE_ABORT));
+        assert!(!helpers(Target::Node).contains(&ABORT));
+        assert!(helpers(Target::Worker).contains(&ABORT));
+        assert!(helpers(Target::Browser).contains(&ABORT));
+    }
+
+    #[test]
+    fn test_rint() {
+        let js = "console.log([0.5,1.5,2.5,-0.5,-1.5,2.4,-2.6,3].map(function(x){\
+                  var r=__cyano_rint(x);return Object.is(r,-0)?\"-0\":r}).join(\" \"))";
+        assert_eq!(eval(js), "0 2 2 -0 -2 2 -3 3\n");
+    }
+
+    #[test]
+    fn test_key_listener() {
+        let src = include_str!("../libcyano/src/input.rs");
+        // The handler counts the presses in its environment, which is kept between events.
+        let js = format!("var l={{}},document={{addEventListener:function(t,f){{l[t]=f}}}};\
+                          function listen(a0,a1,a2){{{}}}\
+                          listen(\"keydown\",[0],function(h,k){{h.get()[0]++;console.log(k.f0,k.f1,h.get()[0])}});\
+                          l.keydown({{key:\"a\",code:\"KeyA\"}});l.keydown({{key:\"B\",code:\"KeyB\"}})",
+                         embedded_js(src, "listen<F"));
+        assert_eq!(eval(&js), "a KeyA 1\nB KeyB 2\n");
+    }
+
+    #[test]
+    fn test_storage() {
+        let src = include_str!("../libcyano/src/storage.rs");
+        let js = format!("var s={{}},localStorage={{setItem:function(k,v){{s[k]=v}},getItem:function(k){{\
+                          return k in s?s[k]:null}},removeItem:function(k){{delete s[k]}}}};\
+                          function set(a0,a1){{{}}}function get(a0){{{}}}function remove(a0){{{}}}\
+                          function as_str(a0){{{}}}\
+                          set(\"greeting\",\"hello\");var g=get(\"greeting\");remove(\"greeting\");\
+                          console.log(g.d,as_str({{get:function(){{return g.f0}}}}),JSON.stringify(get(\"greeting\")))",
+                         embedded_js(src, "set"), embedded_js(src, "get"), embedded_js(src, "remove"),
+                         embedded_js(include_str!("../libcyano/src/string.rs"), "as_str"));
+        assert_eq!(eval(&js), "0 hello {\"d\":1}\n");
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        let src = include_str!("../libcyano/src/text.rs");
+        let js = format!("function encode(a0){{{}}}function decode(a0){{{}}}\
+                          [\"abc\",\"h☃\"].forEach(function(s){{\
+                              var b=encode(s);console.log(JSON.stringify(b),decode({{get:function(){{return b}}}})===s)\
+                          }})",
+                         embedded_js(src, "string_to_bytes"), embedded_js(src, "bytes_to_string"));
+        assert_eq!(eval(&js), "[97,98,99] true\n[104,226,152,131] true\n");
+    }
+}